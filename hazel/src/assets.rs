@@ -0,0 +1,104 @@
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+	sync::{
+		mpsc::{self, Receiver, Sender},
+		Arc, OnceLock,
+	},
+	thread,
+};
+
+use image::{DynamicImage, ImageResult, RgbaImage};
+use wgpu::{Device, Queue};
+
+use crate::{core_warn, geometry::Size, renderer::Texture2D};
+
+/// A texture that is loaded in the background. Until loading finishes, and for good if it fails,
+/// the handle resolves to a 1x1 white placeholder.
+#[derive(Clone)]
+pub struct TextureHandle {
+	texture: Arc<OnceLock<Texture2D>>,
+	placeholder: Arc<Texture2D>,
+}
+
+impl TextureHandle {
+	#[must_use]
+	pub fn get(&self) -> &Texture2D {
+		self.texture.get().unwrap_or(&self.placeholder)
+	}
+
+	#[must_use]
+	pub fn is_loaded(&self) -> bool {
+		self.texture.get().is_some()
+	}
+}
+
+struct DecodedImage {
+	path: PathBuf,
+	texture: Arc<OnceLock<Texture2D>>,
+	image: ImageResult<RgbaImage>,
+}
+
+/// Loads textures from image files and caches them by path, so each file is decoded once.
+///
+/// Files are decoded on a background thread and uploaded to the GPU at the start of the next
+/// frame after decoding finishes.
+pub struct AssetManager {
+	placeholder: Arc<Texture2D>,
+	textures: HashMap<PathBuf, TextureHandle>,
+	sender: Sender<DecodedImage>,
+	receiver: Receiver<DecodedImage>,
+}
+
+impl AssetManager {
+	pub(crate) fn new(device: &Device, queue: &Queue) -> Self {
+		let placeholder = Texture2D::from_rgba8(device, queue, Size::new(1, 1), &[u8::MAX; 4]);
+		let (sender, receiver) = mpsc::channel();
+		AssetManager {
+			placeholder: Arc::new(placeholder),
+			textures: HashMap::new(),
+			sender,
+			receiver,
+		}
+	}
+
+	/// Returns the cached texture for `path`, or starts loading it.
+	pub fn get_or_load(&mut self, path: impl AsRef<Path>) -> TextureHandle {
+		let path = path.as_ref();
+		if let Some(handle) = self.textures.get(path) {
+			return handle.clone();
+		}
+
+		let handle = TextureHandle {
+			texture: Arc::new(OnceLock::new()),
+			placeholder: self.placeholder.clone(),
+		};
+		self.textures.insert(path.to_path_buf(), handle.clone());
+
+		let path = path.to_path_buf();
+		let texture = handle.texture.clone();
+		let sender = self.sender.clone();
+		thread::spawn(move || {
+			let image = image::open(&path).map(DynamicImage::into_rgba8);
+			sender.send(DecodedImage { path, texture, image }).ok();
+		});
+
+		handle
+	}
+
+	/// Uploads the textures that finished decoding since the last call.
+	pub(crate) fn upload_decoded(&mut self, device: &Device, queue: &Queue) {
+		for DecodedImage { path, texture, image } in self.receiver.try_iter() {
+			match image {
+				Ok(image) => {
+					let size = Size::new(image.width(), image.height());
+					texture.set(Texture2D::from_rgba8(device, queue, size, &image)).ok();
+				},
+				Err(error) => {
+					let path = path.display();
+					core_warn!("Could not load texture {path}: {error}");
+				},
+			}
+		}
+	}
+}
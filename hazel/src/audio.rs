@@ -0,0 +1,124 @@
+use std::{cell::Cell, fs::File, io::BufReader, path::Path, rc::Rc};
+
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+
+use crate::core_warn;
+
+struct Playback {
+	sink: Sink,
+	volume: Cell<f32>,
+}
+
+/// A playing sound. Dropping the handle does not stop the sound.
+#[derive(Clone)]
+pub struct Sound {
+	playback: Rc<Playback>,
+	master_volume: Rc<Cell<f32>>,
+}
+
+impl Sound {
+	#[must_use]
+	pub fn volume(&self) -> f32 {
+		self.playback.volume.get()
+	}
+
+	/// Sets the volume of this sound, where `1.0` is the volume of the file. The master volume
+	/// is applied on top.
+	pub fn set_volume(&self, volume: f32) {
+		self.playback.volume.set(volume);
+		self.playback.sink.set_volume(volume * self.master_volume.get());
+	}
+
+	pub fn pause(&self) {
+		self.playback.sink.pause();
+	}
+
+	pub fn resume(&self) {
+		self.playback.sink.play();
+	}
+
+	pub fn stop(&self) {
+		self.playback.sink.stop();
+	}
+
+	#[must_use]
+	pub fn is_finished(&self) -> bool {
+		self.playback.sink.empty()
+	}
+}
+
+/// Sound playback on the default output device.
+///
+/// If there is no output device, a warning is logged once and playback does nothing.
+pub struct Audio {
+	/// The stream stops playing when dropped, so it is kept alongside its handle.
+	output: Option<(OutputStream, OutputStreamHandle)>,
+	master_volume: Rc<Cell<f32>>,
+	playing: Vec<Rc<Playback>>,
+}
+
+impl Audio {
+	pub(crate) fn new() -> Self {
+		let output = OutputStream::try_default()
+			.inspect_err(|error| core_warn!("Audio playback unavailable: {error}"))
+			.ok();
+		Audio { output, master_volume: Rc::new(Cell::new(1.0)), playing: Vec::new() }
+	}
+
+	/// Plays the sound file at `path` once. Returns `None` if there is no output device or the
+	/// file cannot be decoded.
+	pub fn play(&mut self, path: &Path) -> Option<Sound> {
+		self.play_source(path, false)
+	}
+
+	/// Plays the sound file at `path` until the returned sound is stopped.
+	pub fn play_looping(&mut self, path: &Path) -> Option<Sound> {
+		self.play_source(path, true)
+	}
+
+	#[must_use]
+	pub fn master_volume(&self) -> f32 {
+		self.master_volume.get()
+	}
+
+	/// Scales the volume of all sounds, including the ones already playing.
+	pub fn set_master_volume(&mut self, volume: f32) {
+		self.master_volume.set(volume);
+		for playback in &self.playing {
+			playback.sink.set_volume(playback.volume.get() * volume);
+		}
+	}
+
+	fn play_source(&mut self, path: &Path, looping: bool) -> Option<Sound> {
+		let (_, handle) = self.output.as_ref()?;
+
+		let source = File::open(path)
+			.map_err(|it| it.to_string())
+			.and_then(|file| Decoder::new(BufReader::new(file)).map_err(|it| it.to_string()))
+			.and_then(|source| {
+				Sink::try_new(handle).map(|sink| (source, sink)).map_err(|it| it.to_string())
+			});
+		let (source, sink) = match source {
+			Ok(it) => it,
+			Err(error) => {
+				let path = path.display();
+				core_warn!("Could not play sound {path}: {error}");
+				return None;
+			},
+		};
+
+		if looping {
+			sink.append(source.repeat_infinite());
+		}
+		else {
+			sink.append(source);
+		}
+		sink.set_volume(self.master_volume.get());
+
+		self.playing.retain(|it| !it.sink.empty());
+		let playback = Rc::new(Playback { sink, volume: Cell::new(1.0) });
+		self.playing.push(playback.clone());
+
+		Some(Sound { playback, master_volume: self.master_volume.clone() })
+	}
+}
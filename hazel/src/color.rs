@@ -0,0 +1,141 @@
+/// An RGBA color with linear components in `0.0..=1.0`.
+///
+/// The byte and hex constructors take sRGB-encoded values, as found in color pickers and image
+/// editors, and convert them to linear. Use [`Color::linear`] to pass linear values directly.
+/// Render targets with an sRGB format encode the linear values back on write.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Color {
+	pub r: f32,
+	pub g: f32,
+	pub b: f32,
+	pub a: f32,
+}
+
+impl Color {
+	pub const BLACK: Color = Color::linear(0.0, 0.0, 0.0, 1.0);
+	pub const BLUE: Color = Color::linear(0.0, 0.0, 1.0, 1.0);
+	pub const CYAN: Color = Color::linear(0.0, 1.0, 1.0, 1.0);
+	pub const GREEN: Color = Color::linear(0.0, 1.0, 0.0, 1.0);
+	pub const MAGENTA: Color = Color::linear(1.0, 0.0, 1.0, 1.0);
+	pub const RED: Color = Color::linear(1.0, 0.0, 0.0, 1.0);
+	pub const TRANSPARENT: Color = Color::linear(0.0, 0.0, 0.0, 0.0);
+	pub const WHITE: Color = Color::linear(1.0, 1.0, 1.0, 1.0);
+	pub const YELLOW: Color = Color::linear(1.0, 1.0, 0.0, 1.0);
+
+	#[must_use]
+	pub const fn linear(r: f32, g: f32, b: f32, a: f32) -> Self {
+		Color { r, g, b, a }
+	}
+
+	/// An opaque color from sRGB bytes.
+	#[must_use]
+	pub fn rgb(r: u8, g: u8, b: u8) -> Self {
+		Color::rgba(r, g, b, u8::MAX)
+	}
+
+	/// A color from sRGB bytes. Alpha is not gamma-encoded and is taken as is.
+	#[must_use]
+	pub fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+		Color {
+			r: srgb_to_linear(r as f32 / 255.0),
+			g: srgb_to_linear(g as f32 / 255.0),
+			b: srgb_to_linear(b as f32 / 255.0),
+			a: a as f32 / 255.0,
+		}
+	}
+
+	/// An opaque color from an sRGB `0xRRGGBB` value.
+	#[must_use]
+	pub fn from_hex(hex: u32) -> Self {
+		let [_, r, g, b] = hex.to_be_bytes();
+		Color::rgb(r, g, b)
+	}
+
+	/// The color as sRGB bytes.
+	#[must_use]
+	pub fn to_rgba8(self) -> [u8; 4] {
+		let encode = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+		[
+			encode(linear_to_srgb(self.r)),
+			encode(linear_to_srgb(self.g)),
+			encode(linear_to_srgb(self.b)),
+			encode(self.a),
+		]
+	}
+
+	#[must_use]
+	pub fn with_alpha(self, a: f32) -> Self {
+		Color { a, ..self }
+	}
+
+	/// The color with its components multiplied by alpha, as premultiplied blending and
+	/// compositing expect.
+	#[must_use]
+	pub fn premultiplied(self) -> Self {
+		Color { r: self.r * self.a, g: self.g * self.a, b: self.b * self.a, a: self.a }
+	}
+}
+
+impl From<Color> for wgpu::Color {
+	fn from(value: Color) -> Self {
+		wgpu::Color { r: value.r as f64, g: value.g as f64, b: value.b as f64, a: value.a as f64 }
+	}
+}
+
+impl From<Color> for [f32; 4] {
+	fn from(value: Color) -> Self {
+		[value.r, value.g, value.b, value.a]
+	}
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+	if value <= 0.04045 {
+		value / 12.92
+	}
+	else {
+		((value + 0.055) / 1.055).powf(2.4)
+	}
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+	if value <= 0.003_130_8 {
+		value * 12.92
+	}
+	else {
+		1.055 * value.powf(1.0 / 2.4) - 0.055
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Color;
+
+	#[test]
+	fn srgb_bytes_round_trip() {
+		for value in u8::MIN..=u8::MAX {
+			let [r, g, b, a] = Color::rgb(value, value, value).to_rgba8();
+			assert_eq!([r, g, b, a], [value, value, value, u8::MAX], "{value}");
+			assert_eq!(Color::rgba(0, 0, 0, value).to_rgba8()[3], value);
+		}
+		// each channel is converted on its own
+		assert_eq!(Color::rgba(1, 128, 254, 64).to_rgba8(), [1, 128, 254, 64]);
+	}
+
+	#[test]
+	fn srgb_bytes_are_linearized() {
+		let gray = Color::rgb(128, 128, 128);
+		assert!((gray.r - 0.2158).abs() < 1e-3, "{gray:?}");
+		assert_eq!(Color::rgb(255, 255, 255), Color::WHITE);
+		assert_eq!(Color::rgb(0, 0, 0), Color::BLACK);
+	}
+
+	#[test]
+	fn hex_is_rrggbb() {
+		assert_eq!(Color::from_hex(0x12_34_56).to_rgba8(), [0x12, 0x34, 0x56, 0xFF]);
+		assert_eq!(Color::from_hex(0xFF_00_00), Color::RED);
+		assert_eq!(Color::from_hex(0x00_FF_00), Color::GREEN);
+		assert_eq!(Color::from_hex(0x00_00_FF), Color::BLUE);
+		// the unused top byte is ignored
+		assert_eq!(Color::from_hex(0xAB_12_34_56), Color::from_hex(0x12_34_56));
+	}
+}
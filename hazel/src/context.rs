@@ -0,0 +1,528 @@
+use std::{
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+use wgpu::{
+	Adapter, AdapterInfo, DepthStencilState, Device, MultisampleState, PresentMode, Queue, Surface,
+	SurfaceConfiguration, TextureFormat,
+};
+use winit::{
+	dpi::{PhysicalPosition, PhysicalSize},
+	window::{CursorGrabMode, Fullscreen, Icon, Window},
+};
+
+use crate::{
+	assets::AssetManager,
+	core_warn,
+	event::EventQueue,
+	geometry::Size,
+	input::Input,
+	layer::LayerId,
+	monitor::MonitorInfo,
+	record::{Recording, Replay},
+	renderer::RendererConfig,
+	time::{FrameStats, Time},
+	Color, ExitReason,
+};
+
+/// Render state that persists across frames until a layer changes it.
+pub(crate) struct RenderSettings {
+	pub(crate) clear_color: Color,
+	/// Whether the render pass starts cleared rather than with the previous contents.
+	pub(crate) clear: bool,
+}
+
+impl Default for RenderSettings {
+	fn default() -> Self {
+		// sRGB #202020
+		RenderSettings { clear_color: Color::linear(0.0144, 0.0144, 0.0144, 1.0), clear: true }
+	}
+}
+
+pub(crate) struct SurfaceContext<'ctx> {
+	pub(crate) surface: &'ctx Surface<'ctx>,
+	pub(crate) config: &'ctx mut SurfaceConfiguration,
+	pub(crate) present_modes: &'ctx [PresentMode],
+}
+
+pub(crate) struct WindowContext<'ctx> {
+	pub(crate) window: &'ctx Arc<Window>,
+	pub(crate) windowed_size: &'ctx mut Option<PhysicalSize<u32>>,
+}
+
+/// Engine state handed to layers during the update and render passes.
+pub struct LayerContext<'ctx> {
+	pub(crate) adapter: &'ctx Adapter,
+	pub(crate) device: &'ctx Device,
+	pub(crate) queue: &'ctx Queue,
+	pub(crate) renderer_config: RendererConfig,
+	pub(crate) size: Size<u32>,
+	pub(crate) scale_factor: f64,
+	/// `None` when running headless.
+	pub(crate) window: Option<WindowContext<'ctx>>,
+	pub(crate) frame_stats: &'ctx FrameStats,
+	pub(crate) render_settings: &'ctx mut RenderSettings,
+	pub(crate) input: &'ctx Input,
+	pub(crate) assets: &'ctx mut AssetManager,
+	#[cfg(feature = "audio")]
+	pub(crate) audio: &'ctx mut crate::audio::Audio,
+	pub(crate) replay: &'ctx mut Replay,
+	pub(crate) layer_timings: &'ctx [(LayerId, String, Duration)],
+	/// Set by [`LayerContext::request_exit`], unless the application is already exiting.
+	pub(crate) exit_reason: &'ctx mut Option<ExitReason>,
+	pub(crate) redraw_deadline: &'ctx mut Option<Instant>,
+	pub(crate) event_queue: &'ctx EventQueue,
+	pub(crate) screenshot_path: &'ctx mut Option<PathBuf>,
+	pub(crate) fixed_timestep: Option<Duration>,
+	pub(crate) interpolation_alpha: f32,
+	/// Events received from a peer, passed to the application after the frame.
+	#[cfg(feature = "net")]
+	pub(crate) remote_events: &'ctx mut Vec<crate::event::Event>,
+	/// `None` when running headless.
+	pub(crate) surface: Option<SurfaceContext<'ctx>>,
+}
+
+impl<'ctx> LayerContext<'ctx> {
+	/// The adapter the device was created from, to query its features, limits and formats.
+	#[must_use]
+	pub fn adapter(&self) -> &'ctx Adapter {
+		self.adapter
+	}
+
+	/// The name, backend and device type of the adapter, e.g. for a diagnostics overlay.
+	#[must_use]
+	pub fn adapter_info(&self) -> AdapterInfo {
+		self.adapter.get_info()
+	}
+
+	#[must_use]
+	pub fn device(&self) -> &'ctx Device {
+		self.device
+	}
+
+	#[must_use]
+	pub fn queue(&self) -> &'ctx Queue {
+		self.queue
+	}
+
+	/// The formats and sample count pipelines drawing in the shared render pass must match.
+	#[must_use]
+	pub fn renderer_config(&self) -> RendererConfig {
+		self.renderer_config
+	}
+
+	/// The format of the texture the shared render pass draws into, see
+	/// [`RendererConfig::format`].
+	#[must_use]
+	pub fn format(&self) -> TextureFormat {
+		self.renderer_config.format
+	}
+
+	/// The size of the render target in physical pixels, which is what render passes and
+	/// viewports work in.
+	#[must_use]
+	pub fn size(&self) -> Size<u32> {
+		self.size
+	}
+
+	/// The window, e.g. to hand its raw window handle to a file dialog or another rendering
+	/// library through [`winit::raw_window_handle::HasWindowHandle`]. `None` when running
+	/// headless.
+	///
+	/// Cloning the `Arc` keeps the `Window` alive, but not the event loop: drop clones by
+	/// [`crate::Application::on_stop`]. On Android the native window behind the handle is gone
+	/// between a suspend and the following resume, along with the surface, so anything created
+	/// from the handle has to be recreated in [`crate::layer::Layer::on_surface_recreated`].
+	#[must_use]
+	pub fn window(&self) -> Option<&'ctx Arc<Window>> {
+		self.window.as_ref().map(|it| it.window)
+	}
+
+	/// The ratio of physical to logical pixels, `1.0` when running headless.
+	#[must_use]
+	pub fn scale_factor(&self) -> f64 {
+		self.scale_factor
+	}
+
+	/// The size of the render target in logical pixels, for laying out UI independent of DPI.
+	#[must_use]
+	pub fn logical_size(&self) -> Size<f32> {
+		Size::new(
+			(self.size.width as f64 / self.scale_factor) as f32,
+			(self.size.height as f64 / self.scale_factor) as f32,
+		)
+	}
+
+	/// The format of the depth buffer attached to the shared render pass, or `None` if
+	/// [`crate::WindowSpec::depth_compare`] is not set.
+	#[must_use]
+	pub fn depth_format(&self) -> Option<TextureFormat> {
+		self.renderer_config.depth_format()
+	}
+
+	/// The depth state for pipelines drawing in the shared render pass, see
+	/// [`RendererConfig::depth_stencil_state`].
+	#[must_use]
+	pub fn depth_stencil_state(&self) -> Option<DepthStencilState> {
+		self.renderer_config.depth_stencil_state()
+	}
+
+	/// The number of samples per pixel of the shared render pass, see
+	/// [`RendererConfig::sample_count`].
+	#[must_use]
+	pub fn sample_count(&self) -> u32 {
+		self.renderer_config.sample_count
+	}
+
+	/// The multisample state for pipelines drawing in the shared render pass.
+	#[must_use]
+	pub fn multisample_state(&self) -> MultisampleState {
+		self.renderer_config.multisample_state()
+	}
+
+	/// The time step since the previous frame, to scale movement and animation by. Clamped to
+	/// [`crate::time::MAX_DELTA_TIME`], unlike [`LayerContext::frame_time`].
+	#[must_use]
+	pub fn delta_time(&self) -> Time {
+		self.fixed_timestep.map_or_else(|| self.frame_stats.delta_time(), Time::from)
+	}
+
+	/// How far the current frame lies between the last fixed update step and the next, from 0
+	/// to 1, to interpolate positions by in `on_render`. Always 1 without
+	/// [`crate::WindowSpec::fixed_timestep`].
+	#[must_use]
+	pub fn interpolation_alpha(&self) -> f32 {
+		self.interpolation_alpha
+	}
+
+	/// Frames per second, averaged over the last second.
+	#[must_use]
+	pub fn fps(&self) -> f32 {
+		self.frame_stats.fps()
+	}
+
+	/// The duration of the previous frame, including present and vsync wait.
+	#[must_use]
+	pub fn frame_time(&self) -> Duration {
+		self.frame_stats.frame_time()
+	}
+
+	/// The GPU time of the shared render pass, see [`FrameStats::gpu_frame_time`].
+	#[must_use]
+	pub fn gpu_frame_time(&self) -> Option<Duration> {
+		self.frame_stats.gpu_frame_time()
+	}
+
+	#[must_use]
+	pub fn frame_stats(&self) -> &'ctx FrameStats {
+		self.frame_stats
+	}
+
+	/// The time each layer spent in `on_update`, `on_render` and `on_event` during the previous
+	/// frame, top of the stack first. Only measured with the `profile` feature, and empty without
+	/// it.
+	#[must_use]
+	pub fn layer_timings(&self) -> Vec<(LayerId, &'ctx str, Duration)> {
+		self.layer_timings
+			.iter()
+			.map(|(id, name, duration)| (*id, name.as_str(), *duration))
+			.collect()
+	}
+
+	#[must_use]
+	pub fn input(&self) -> &'ctx Input {
+		self.input
+	}
+
+	/// The texture cache, to load textures without stalling the frame.
+	pub fn assets(&mut self) -> &mut AssetManager {
+		self.assets
+	}
+
+	/// Sound playback, e.g. to play a sound in response to an event.
+	#[cfg(feature = "audio")]
+	pub fn audio(&mut self) -> &mut crate::audio::Audio {
+		self.audio
+	}
+
+	#[must_use]
+	pub fn is_recording(&self) -> bool {
+		self.replay.is_recording()
+	}
+
+	/// Starts recording every dispatched event, discarding a recording that is in progress.
+	pub fn start_recording(&mut self) {
+		self.replay.start_recording();
+	}
+
+	/// Starts recording like [`LayerContext::start_recording`], and writes the recording to `path`
+	/// when it stops, or when the application exits while still recording.
+	#[cfg(feature = "serde")]
+	pub fn start_recording_to(&mut self, path: impl Into<PathBuf>) {
+		self.replay.start_recording_to(path.into());
+	}
+
+	/// Stops recording and returns the events recorded so far, or `None` if not recording. A
+	/// recording started with a path is also written there.
+	pub fn stop_recording(&mut self) -> Option<Recording> {
+		self.replay.stop_recording()
+	}
+
+	#[must_use]
+	pub fn is_playing(&self) -> bool {
+		self.replay.is_playing()
+	}
+
+	/// Replays `recording` from the start, with the original timing. Live input is ignored until
+	/// it finishes or [`LayerContext::stop_playing`] is called. Returns `false` if the recording
+	/// was made with an incompatible version.
+	pub fn play_recording(&mut self, recording: Recording) -> bool {
+		self.replay.play(recording)
+	}
+
+	pub fn stop_playing(&mut self) {
+		self.replay.stop_playing();
+	}
+
+	#[must_use]
+	pub fn clear_color(&self) -> Color {
+		self.render_settings.clear_color
+	}
+
+	/// Sets the color the shared render pass is cleared to, starting with the current frame if
+	/// called from `on_update`. The clear happens once, before the bottom-most layer renders;
+	/// every layer after that draws over the layers below it. The color stays until changed.
+	pub fn set_clear_color(&mut self, color: Color) {
+		self.render_settings.clear_color = color;
+	}
+
+	#[must_use]
+	pub fn is_clear_enabled(&self) -> bool {
+		self.render_settings.clear
+	}
+
+	/// Disabling the clear loads the previous contents of the render target instead, so layers
+	/// can draw over the last frame, e.g. for trails. Like the clear color this applies to the
+	/// whole shared render pass, so it is best left to the bottom-most layer.
+	///
+	/// The surface is usually double or triple buffered, so without a clear the frame starts with
+	/// whatever an older frame left in that buffer, or undefined contents on some platforms. An
+	/// explicit clear is usually still wanted.
+	pub fn set_clear_enabled(&mut self, clear: bool) {
+		self.render_settings.clear = clear;
+	}
+
+	/// The present mode of the window surface, or `None` when running headless.
+	#[must_use]
+	pub fn present_mode(&self) -> Option<PresentMode> {
+		self.surface.as_ref().map(|it| it.config.present_mode)
+	}
+
+	/// Switches the present mode, e.g. to toggle vsync, and reconfigures the surface right away.
+	/// Modes the adapter does not support are ignored with a warning.
+	pub fn set_present_mode(&mut self, present_mode: PresentMode) {
+		let Some(surface) = &mut self.surface
+		else {
+			core_warn!("Cannot set present mode {present_mode:?} without a window surface");
+			return;
+		};
+
+		let is_auto = matches!(present_mode, PresentMode::AutoVsync | PresentMode::AutoNoVsync);
+		if !is_auto && !surface.present_modes.contains(&present_mode) {
+			core_warn!("Present mode {present_mode:?} is not supported by the adapter");
+			return;
+		}
+
+		surface.config.present_mode = present_mode;
+		surface.surface.configure(self.device, surface.config);
+	}
+
+	/// Exits the application once the current frame is finished, e.g. from a "Quit" menu item in
+	/// `on_update`. The run returns [`crate::ExitReason::LayerRequested`].
+	pub fn request_exit(&mut self) {
+		self.exit_reason.get_or_insert(ExitReason::LayerRequested);
+	}
+
+	/// Schedules another frame. Only needed with [`crate::RedrawPolicy::OnDemand`], e.g. while
+	/// an animation is running.
+	pub fn request_redraw(&self) {
+		if let Some(WindowContext { window, .. }) = &self.window {
+			window.request_redraw();
+		}
+	}
+
+	/// Schedules a frame after `delay` without redrawing in between, e.g. for a blinking cursor
+	/// with [`crate::RedrawPolicy::OnDemand`]. Of several pending requests the earliest wins.
+	pub fn request_redraw_in(&mut self, delay: Duration) {
+		let deadline = Instant::now() + delay;
+		if self.redraw_deadline.is_none_or(|it| deadline < it) {
+			*self.redraw_deadline = Some(deadline);
+		}
+	}
+
+	/// A queue to post events to the main loop with, which can be handed to other threads.
+	#[must_use]
+	pub fn event_queue(&self) -> EventQueue {
+		self.event_queue.clone()
+	}
+
+	/// Saves the current frame as a PNG once it is rendered. The file is written on a background
+	/// thread, and failures are logged.
+	pub fn capture_screenshot(&mut self, path: impl Into<PathBuf>) {
+		*self.screenshot_path = Some(path.into());
+	}
+
+	/// Locks the cursor to the window, e.g. for first-person camera control with
+	/// [`Event::MouseMotionRaw`](crate::event::Event::MouseMotionRaw). Where the platform cannot
+	/// lock the cursor in place, it is confined to the window instead.
+	pub fn set_cursor_grab(&self, grab: bool) {
+		let Some(WindowContext { window, .. }) = &self.window
+		else {
+			return;
+		};
+
+		let result = if grab {
+			window
+				.set_cursor_grab(CursorGrabMode::Locked)
+				.or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined))
+		}
+		else {
+			window.set_cursor_grab(CursorGrabMode::None)
+		};
+		if let Err(error) = result {
+			core_warn!("Could not change cursor grab: {error}");
+		}
+	}
+
+	pub fn set_cursor_visible(&self, visible: bool) {
+		if let Some(WindowContext { window, .. }) = &self.window {
+			window.set_cursor_visible(visible);
+		}
+	}
+
+	#[must_use]
+	pub fn is_fullscreen(&self) -> bool {
+		self.window.as_ref().is_some_and(|it| it.window.fullscreen().is_some())
+	}
+
+	/// Puts the window into the given fullscreen mode, or back into windowed mode with `None`,
+	/// restoring the size it had before entering fullscreen. The surface is reconfigured when the
+	/// resulting resize event arrives.
+	pub fn set_fullscreen(&mut self, fullscreen: Option<Fullscreen>) {
+		let Some(WindowContext { window, windowed_size }) = &mut self.window
+		else {
+			return;
+		};
+
+		if fullscreen.is_some() {
+			if window.fullscreen().is_none() {
+				**windowed_size = Some(window.inner_size());
+			}
+			window.set_fullscreen(fullscreen);
+		}
+		else {
+			window.set_fullscreen(None);
+			if let Some(size) = windowed_size.take() {
+				let _ = window.request_inner_size(size);
+			}
+		}
+	}
+
+	/// Switches between windowed mode and borderless fullscreen on the current monitor, e.g. when
+	/// a layer sees Alt+Enter.
+	pub fn toggle_fullscreen(&mut self) {
+		let Some(WindowContext { window, .. }) = &self.window
+		else {
+			return;
+		};
+
+		if window.fullscreen().is_some() {
+			self.set_fullscreen(None);
+		}
+		else {
+			let monitor = window.current_monitor();
+			self.set_fullscreen(Some(Fullscreen::Borderless(monitor)));
+		}
+	}
+
+	/// The connected monitors, queried anew on every call. Empty when running headless.
+	#[must_use]
+	pub fn available_monitors(&self) -> Vec<MonitorInfo> {
+		let Some(WindowContext { window, .. }) = &self.window
+		else {
+			return Vec::new();
+		};
+
+		let primary = window.primary_monitor();
+		window.available_monitors().map(|it| MonitorInfo::new(it, primary.as_ref())).collect()
+	}
+
+	/// The primary monitor. `None` when running headless or where the platform has no primary
+	/// monitor, such as Wayland.
+	#[must_use]
+	pub fn primary_monitor(&self) -> Option<MonitorInfo> {
+		let primary = self.window.as_ref()?.window.primary_monitor()?;
+		Some(MonitorInfo::new(primary.clone(), Some(&primary)))
+	}
+
+	/// The monitor the window is mostly on.
+	#[must_use]
+	pub fn current_monitor(&self) -> Option<MonitorInfo> {
+		let window = &self.window.as_ref()?.window;
+		let primary = window.primary_monitor();
+		Some(MonitorInfo::new(window.current_monitor()?, primary.as_ref()))
+	}
+
+	/// Puts the window into borderless fullscreen on `monitor`, e.g. one picked from
+	/// [`LayerContext::available_monitors`] in a settings menu.
+	pub fn set_fullscreen_on(&mut self, monitor: &MonitorInfo) {
+		self.set_fullscreen(Some(Fullscreen::Borderless(Some(monitor.handle().clone()))));
+	}
+
+	/// Centers the window on `monitor`. Has no effect in fullscreen, and on Wayland, where
+	/// clients cannot position their windows.
+	pub fn move_to_monitor(&self, monitor: &MonitorInfo) {
+		let Some(WindowContext { window, .. }) = &self.window
+		else {
+			return;
+		};
+
+		// half of any u32 fits in an i32
+		let size = window.outer_size();
+		let x =
+			monitor.position.x + (monitor.size.width.saturating_sub(size.width) / 2).cast_signed();
+		let y = monitor.position.y
+			+ (monitor.size.height.saturating_sub(size.height) / 2).cast_signed();
+		window.set_outer_position(PhysicalPosition::new(x, y));
+	}
+
+	pub fn set_window_title(&self, title: &str) {
+		if let Some(WindowContext { window, .. }) = &self.window {
+			window.set_title(title);
+		}
+	}
+
+	/// Sets the window icon from an image file. If the file cannot be read or decoded, a warning
+	/// is logged and the icon is left unchanged.
+	pub fn set_window_icon(&self, path: &Path) {
+		let Some(WindowContext { window, .. }) = &self.window
+		else {
+			return;
+		};
+
+		let icon = image::open(path).map_err(|it| it.to_string()).and_then(|image| {
+			let image = image.into_rgba8();
+			let (width, height) = image.dimensions();
+			Icon::from_rgba(image.into_raw(), width, height).map_err(|it| it.to_string())
+		});
+		match icon {
+			Ok(icon) => window.set_window_icon(Some(icon)),
+			Err(error) => {
+				let path = path.display();
+				core_warn!("Could not load window icon {path}: {error}");
+			},
+		}
+	}
+}
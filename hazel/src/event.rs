@@ -1,11 +1,341 @@
-#[derive(Debug)]
+use std::{
+	any::Any,
+	fmt::{self, Display},
+	path::PathBuf,
+	sync::{
+		mpsc::{self, Receiver, Sender},
+		Arc, Mutex, PoisonError,
+	},
+};
+
+use bitflags::bitflags;
+use winit::event_loop::EventLoopProxy;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Event {
-	KeyPressed { key: crate::Key, is_repeat: bool },
-	KeyReleased { key: crate::Key },
+	/// `key` is the logical key, following the keyboard layout, and `physical` the key at that
+	/// position on a US layout. See [`crate::KeyCode`] for which to use when.
+	KeyPressed {
+		key: crate::KeyCode,
+		physical: crate::KeyCode,
+		is_repeat: bool,
+	},
+	KeyReleased {
+		key: crate::KeyCode,
+		physical: crate::KeyCode,
+	},
+	KeyTyped(char),
+	ModifiersChanged(crate::input::Modifiers),
 	MouseButtonPressed(crate::MouseButton),
 	MouseButtonReleased(crate::MouseButton),
-	MouseMoved { x: f32, y: f32 },
-	MouseScrolled { x_offset: f32, y_offset: f32 },
+	MouseMoved {
+		x: f32,
+		y: f32,
+	},
+	MouseMotionRaw {
+		dx: f32,
+		dy: f32,
+	},
+	MouseScrolled {
+		x_offset: f32,
+		y_offset: f32,
+		is_line_delta: bool,
+	},
 	WindowClose,
-	WindowResize { width: u32, height: u32 },
+	WindowResize {
+		width: u32,
+		height: u32,
+		scale_factor: f64,
+	},
+	WindowScaleChanged {
+		scale_factor: f64,
+	},
+	/// A file was dropped on the window.
+	FileDropped(PathBuf),
+	/// A file is dragged over the window. Followed by `FileDropped` or `FileHoverCancelled`.
+	FileHovered(PathBuf),
+	FileHoverCancelled,
+	GamepadConnected(crate::GamepadId),
+	GamepadDisconnected(crate::GamepadId),
+	GamepadButtonPressed {
+		id: crate::GamepadId,
+		button: crate::GamepadButton,
+	},
+	GamepadButtonReleased {
+		id: crate::GamepadId,
+		button: crate::GamepadButton,
+	},
+	GamepadAxisMoved {
+		id: crate::GamepadId,
+		axis: crate::GamepadAxis,
+		value: f32,
+	},
+	/// An application-defined event posted through an [`EventQueue`]. Not serializable, so it is
+	/// neither recorded nor sent to network peers.
+	#[cfg_attr(feature = "serde", serde(skip))]
+	User(UserEvent),
+}
+
+bitflags! {
+	/// Groups of events, used by [`crate::layer::Layer::event_mask`] to pick which events a layer
+	/// receives. An event can belong to several categories.
+	#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+	pub struct EventCategory: u8 {
+		const WINDOW = 1 << 0;
+		const INPUT = 1 << 1;
+		const KEYBOARD = 1 << 2;
+		const MOUSE = 1 << 3;
+		const GAMEPAD = 1 << 4;
+		const USER = 1 << 5;
+	}
+}
+
+/// The payload of [`Event::User`], any value that can be sent between threads.
+///
+/// Payloads are shared, not cloned, when the event is cloned, and events compare equal only if
+/// they share the same payload.
+#[derive(Clone)]
+pub struct UserEvent(Arc<dyn Any + Send + Sync>);
+
+impl UserEvent {
+	pub fn new(payload: impl Any + Send + Sync) -> Self {
+		UserEvent(Arc::new(payload))
+	}
+
+	#[must_use]
+	pub fn is<T: Any>(&self) -> bool {
+		self.0.is::<T>()
+	}
+
+	#[must_use]
+	pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+		self.0.downcast_ref()
+	}
+}
+
+impl fmt::Debug for UserEvent {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("UserEvent(..)")
+	}
+}
+
+impl PartialEq for UserEvent {
+	fn eq(&self, other: &Self) -> bool {
+		Arc::ptr_eq(&self.0, &other.0)
+	}
+}
+
+/// Posts events to the main loop from any thread, e.g. when a background load finishes. Clones
+/// post to the same loop.
+///
+/// Posted events are dispatched like window events, through the application and the layer
+/// stack, once the loop wakes up. A [`Harness`](crate::Harness) has no event loop, so it only
+/// applies them to the input state at the start of the next frame.
+#[derive(Clone)]
+pub struct EventQueue {
+	sender: Sender<Event>,
+	/// Wakes the event loop while it waits for events, set once the loop exists.
+	waker: Arc<Mutex<Option<EventLoopProxy<()>>>>,
+}
+
+impl EventQueue {
+	pub(crate) fn new() -> (Self, Receiver<Event>) {
+		let (sender, receiver) = mpsc::channel();
+		(EventQueue { sender, waker: Arc::default() }, receiver)
+	}
+
+	pub(crate) fn set_waker(&self, proxy: EventLoopProxy<()>) {
+		self.waker.lock().unwrap_or_else(PoisonError::into_inner).replace(proxy);
+	}
+
+	/// Posts an event. Does nothing once the main loop has stopped.
+	pub fn post(&self, event: Event) {
+		if self.sender.send(event).is_ok() {
+			if let Some(proxy) = &*self.waker.lock().unwrap_or_else(PoisonError::into_inner) {
+				// fails only if the loop has exited already
+				let _ = proxy.send_event(());
+			}
+		}
+	}
+
+	/// Posts an [`Event::User`] with the given payload.
+	pub fn post_user(&self, payload: impl Any + Send + Sync) {
+		self.post(Event::User(UserEvent::new(payload)));
+	}
+}
+
+impl Event {
+	/// The name of the event variant, e.g. `"KeyPressed"`.
+	#[must_use]
+	pub fn name(&self) -> &'static str {
+		match self {
+			Event::KeyPressed { .. } => "KeyPressed",
+			Event::KeyReleased { .. } => "KeyReleased",
+			Event::KeyTyped(_) => "KeyTyped",
+			Event::ModifiersChanged(_) => "ModifiersChanged",
+			Event::MouseButtonPressed(_) => "MouseButtonPressed",
+			Event::MouseButtonReleased(_) => "MouseButtonReleased",
+			Event::MouseMoved { .. } => "MouseMoved",
+			Event::MouseMotionRaw { .. } => "MouseMotionRaw",
+			Event::MouseScrolled { .. } => "MouseScrolled",
+			Event::WindowClose => "WindowClose",
+			Event::WindowResize { .. } => "WindowResize",
+			Event::WindowScaleChanged { .. } => "WindowScaleChanged",
+			Event::FileDropped(_) => "FileDropped",
+			Event::FileHovered(_) => "FileHovered",
+			Event::FileHoverCancelled => "FileHoverCancelled",
+			Event::GamepadConnected(_) => "GamepadConnected",
+			Event::GamepadDisconnected(_) => "GamepadDisconnected",
+			Event::GamepadButtonPressed { .. } => "GamepadButtonPressed",
+			Event::GamepadButtonReleased { .. } => "GamepadButtonReleased",
+			Event::GamepadAxisMoved { .. } => "GamepadAxisMoved",
+			Event::User(_) => "User",
+		}
+	}
+
+	#[must_use]
+	pub fn category(&self) -> EventCategory {
+		match self {
+			Event::KeyPressed { .. }
+			| Event::KeyReleased { .. }
+			| Event::KeyTyped(_)
+			| Event::ModifiersChanged(_) => EventCategory::INPUT | EventCategory::KEYBOARD,
+			Event::MouseButtonPressed(_)
+			| Event::MouseButtonReleased(_)
+			| Event::MouseMoved { .. }
+			| Event::MouseMotionRaw { .. }
+			| Event::MouseScrolled { .. } => EventCategory::INPUT | EventCategory::MOUSE,
+			Event::WindowClose
+			| Event::WindowResize { .. }
+			| Event::WindowScaleChanged { .. }
+			| Event::FileDropped(_)
+			| Event::FileHovered(_)
+			| Event::FileHoverCancelled => EventCategory::WINDOW,
+			Event::GamepadConnected(_)
+			| Event::GamepadDisconnected(_)
+			| Event::GamepadButtonPressed { .. }
+			| Event::GamepadButtonReleased { .. }
+			| Event::GamepadAxisMoved { .. } => EventCategory::INPUT | EventCategory::GAMEPAD,
+			Event::User(_) => EventCategory::USER,
+		}
+	}
+
+	#[must_use]
+	pub fn is_in_category(&self, category: EventCategory) -> bool {
+		self.category().intersects(category)
+	}
+}
+
+/// Formats events concisely for logs, e.g. `KeyPressed(Tab, repeat)` or `MouseMoved(120.5, 80.0)`.
+impl Display for Event {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let name = self.name();
+		match self {
+			Event::KeyPressed { key, is_repeat: true, .. } => {
+				write!(f, "{name}({key}, repeat)")
+			},
+			Event::KeyPressed { key, is_repeat: false, .. } | Event::KeyReleased { key, .. } => {
+				write!(f, "{name}({key})")
+			},
+			Event::KeyTyped(character) => write!(f, "{name}({character:?})"),
+			Event::ModifiersChanged(modifiers) => {
+				let names = [
+					(modifiers.ctrl, "ctrl"),
+					(modifiers.shift, "shift"),
+					(modifiers.alt, "alt"),
+					(modifiers.super_key, "super"),
+				]
+				.into_iter()
+				.filter_map(|(pressed, name)| pressed.then_some(name))
+				.collect::<Vec<_>>();
+				if names.is_empty() {
+					write!(f, "{name}(none)")
+				}
+				else {
+					write!(f, "{name}({})", names.join("+"))
+				}
+			},
+			Event::MouseButtonPressed(button) | Event::MouseButtonReleased(button) => {
+				write!(f, "{name}({button:?})")
+			},
+			Event::MouseMoved { x, y } => write!(f, "{name}({x:?}, {y:?})"),
+			Event::MouseMotionRaw { dx, dy } => write!(f, "{name}({dx:?}, {dy:?})"),
+			Event::MouseScrolled { x_offset, y_offset, is_line_delta: true } => {
+				write!(f, "{name}({x_offset:?}, {y_offset:?}, lines)")
+			},
+			Event::MouseScrolled { x_offset, y_offset, is_line_delta: false } => {
+				write!(f, "{name}({x_offset:?}, {y_offset:?})")
+			},
+			Event::WindowClose | Event::FileHoverCancelled => write!(f, "{name}"),
+			Event::WindowResize { width, height, scale_factor } => {
+				write!(f, "{name}({width}x{height} @ {scale_factor:?})")
+			},
+			Event::WindowScaleChanged { scale_factor } => write!(f, "{name}({scale_factor:?})"),
+			Event::FileDropped(path) | Event::FileHovered(path) => {
+				write!(f, "{name}({})", path.display())
+			},
+			Event::GamepadConnected(id) | Event::GamepadDisconnected(id) => {
+				write!(f, "{name}({})", id.0)
+			},
+			Event::GamepadButtonPressed { id, button }
+			| Event::GamepadButtonReleased { id, button } => {
+				write!(f, "{name}({}, {button:?})", id.0)
+			},
+			Event::GamepadAxisMoved { id, axis, value } => {
+				write!(f, "{name}({}, {axis:?}, {value:?})", id.0)
+			},
+			Event::User(payload) => write!(f, "{name}({payload:?})"),
+		}
+	}
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+	use std::path::PathBuf;
+
+	use super::Event;
+	use crate::{input::Modifiers, GamepadAxis, GamepadButton, GamepadId, KeyCode, MouseButton};
+
+	#[test]
+	fn events_round_trip_through_json() {
+		let id = GamepadId(1);
+		let events = [
+			Event::KeyPressed {
+				key: KeyCode::Character('!'),
+				physical: KeyCode::Digit1,
+				is_repeat: true,
+			},
+			Event::KeyReleased { key: KeyCode::A, physical: KeyCode::Q },
+			Event::KeyTyped('é'),
+			Event::ModifiersChanged(Modifiers {
+				ctrl: true,
+				shift: false,
+				alt: true,
+				super_key: false,
+			}),
+			Event::MouseButtonPressed(MouseButton::Left),
+			Event::MouseButtonReleased(MouseButton::Other(9)),
+			Event::MouseMoved { x: 120.5, y: -3.25 },
+			Event::MouseMotionRaw { dx: 0.5, dy: -1.0 },
+			Event::MouseScrolled { x_offset: 0.0, y_offset: 3.0, is_line_delta: true },
+			Event::WindowClose,
+			Event::WindowResize { width: 1280, height: 720, scale_factor: 1.5 },
+			Event::WindowScaleChanged { scale_factor: 2.0 },
+			Event::FileDropped(PathBuf::from("assets/level 1.json")),
+			Event::FileHovered(PathBuf::from("/tmp/a.png")),
+			Event::FileHoverCancelled,
+			Event::GamepadConnected(id),
+			Event::GamepadDisconnected(id),
+			Event::GamepadButtonPressed { id, button: GamepadButton::South },
+			Event::GamepadButtonReleased { id, button: GamepadButton::DPadLeft },
+			Event::GamepadAxisMoved { id, axis: GamepadAxis::LeftStickX, value: -0.75 },
+		];
+
+		for event in events {
+			let json = serde_json::to_string(&event).unwrap();
+			let parsed: Event = serde_json::from_str(&json).unwrap();
+			assert_eq!(parsed, event, "{json}");
+		}
+	}
 }
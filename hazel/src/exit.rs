@@ -0,0 +1,31 @@
+use std::process::{ExitCode, Termination};
+
+/// Why the main loop stopped, as returned by [`crate::run`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExitReason {
+	/// The window was closed and [`crate::Application::on_window_close`] exited.
+	UserClosed,
+	/// The application exited from one of its other callbacks.
+	ApplicationRequested,
+	/// A layer exited the event loop.
+	LayerRequested,
+	/// The engine stopped because of an error. `run` returns the error itself as `Err`.
+	Error,
+}
+
+impl ExitReason {
+	/// A process exit code: success for every reason but [`ExitReason::Error`].
+	#[must_use]
+	pub fn exit_code(self) -> ExitCode {
+		match self {
+			ExitReason::Error => ExitCode::FAILURE,
+			_ => ExitCode::SUCCESS,
+		}
+	}
+}
+
+impl Termination for ExitReason {
+	fn report(self) -> ExitCode {
+		self.exit_code()
+	}
+}
@@ -0,0 +1,128 @@
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GamepadId(pub usize);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GamepadButton {
+	South,
+	East,
+	North,
+	West,
+	C,
+	Z,
+	LeftTrigger,
+	LeftTrigger2,
+	RightTrigger,
+	RightTrigger2,
+	Select,
+	Start,
+	Mode,
+	LeftThumb,
+	RightThumb,
+	DPadUp,
+	DPadDown,
+	DPadLeft,
+	DPadRight,
+	Unknown,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GamepadAxis {
+	LeftStickX,
+	LeftStickY,
+	LeftZ,
+	RightStickX,
+	RightStickY,
+	RightZ,
+	DPadX,
+	DPadY,
+	Unknown,
+}
+
+#[cfg(feature = "gamepad")]
+pub(crate) use self::gilrs_support::{poll_interval, translate};
+
+#[cfg(feature = "gamepad")]
+mod gilrs_support {
+	use std::time::Duration;
+
+	use gilrs::{Axis, Button, EventType, Gilrs};
+
+	use super::{GamepadAxis, GamepadButton, GamepadId};
+	use crate::event::Event;
+
+	/// How often to poll for gamepad events while the event loop would otherwise wait, since
+	/// gilrs does not wake it. Often enough for play while a gamepad is connected, and slowly
+	/// otherwise, to notice a gamepad being connected.
+	pub(crate) fn poll_interval(gilrs: &Gilrs) -> Duration {
+		if gilrs.gamepads().next().is_some() {
+			Duration::from_millis(8)
+		}
+		else {
+			Duration::from_millis(500)
+		}
+	}
+
+	pub(crate) fn translate(event: gilrs::Event) -> Option<Event> {
+		let id = GamepadId(event.id.into());
+		match event.event {
+			EventType::Connected => Some(Event::GamepadConnected(id)),
+			EventType::Disconnected => Some(Event::GamepadDisconnected(id)),
+			EventType::ButtonPressed(button, _) => {
+				Some(Event::GamepadButtonPressed { id, button: button.into() })
+			},
+			EventType::ButtonReleased(button, _) => {
+				Some(Event::GamepadButtonReleased { id, button: button.into() })
+			},
+			EventType::AxisChanged(axis, value, _) => {
+				Some(Event::GamepadAxisMoved { id, axis: axis.into(), value })
+			},
+			_ => None,
+		}
+	}
+
+	impl From<Button> for GamepadButton {
+		fn from(value: Button) -> Self {
+			match value {
+				Button::South => GamepadButton::South,
+				Button::East => GamepadButton::East,
+				Button::North => GamepadButton::North,
+				Button::West => GamepadButton::West,
+				Button::C => GamepadButton::C,
+				Button::Z => GamepadButton::Z,
+				Button::LeftTrigger => GamepadButton::LeftTrigger,
+				Button::LeftTrigger2 => GamepadButton::LeftTrigger2,
+				Button::RightTrigger => GamepadButton::RightTrigger,
+				Button::RightTrigger2 => GamepadButton::RightTrigger2,
+				Button::Select => GamepadButton::Select,
+				Button::Start => GamepadButton::Start,
+				Button::Mode => GamepadButton::Mode,
+				Button::LeftThumb => GamepadButton::LeftThumb,
+				Button::RightThumb => GamepadButton::RightThumb,
+				Button::DPadUp => GamepadButton::DPadUp,
+				Button::DPadDown => GamepadButton::DPadDown,
+				Button::DPadLeft => GamepadButton::DPadLeft,
+				Button::DPadRight => GamepadButton::DPadRight,
+				Button::Unknown => GamepadButton::Unknown,
+			}
+		}
+	}
+
+	impl From<Axis> for GamepadAxis {
+		fn from(value: Axis) -> Self {
+			match value {
+				Axis::LeftStickX => GamepadAxis::LeftStickX,
+				Axis::LeftStickY => GamepadAxis::LeftStickY,
+				Axis::LeftZ => GamepadAxis::LeftZ,
+				Axis::RightStickX => GamepadAxis::RightStickX,
+				Axis::RightStickY => GamepadAxis::RightStickY,
+				Axis::RightZ => GamepadAxis::RightZ,
+				Axis::DPadX => GamepadAxis::DPadX,
+				Axis::DPadY => GamepadAxis::DPadY,
+				Axis::Unknown => GamepadAxis::Unknown,
+			}
+		}
+	}
+}
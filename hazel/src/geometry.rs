@@ -0,0 +1,49 @@
+use crate::math::Vec2;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Size<T> {
+	pub width: T,
+	pub height: T,
+}
+
+impl<T> Size<T> {
+	pub const fn new(width: T, height: T) -> Self {
+		Size { width, height }
+	}
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Position<T> {
+	pub x: T,
+	pub y: T,
+}
+
+impl<T> Position<T> {
+	pub const fn new(x: T, y: T) -> Self {
+		Position { x, y }
+	}
+}
+
+impl From<Size<f32>> for Vec2 {
+	fn from(value: Size<f32>) -> Self {
+		Vec2::new(value.width, value.height)
+	}
+}
+
+impl From<Vec2> for Size<f32> {
+	fn from(value: Vec2) -> Self {
+		Size::new(value.x, value.y)
+	}
+}
+
+impl From<Position<f32>> for Vec2 {
+	fn from(value: Position<f32>) -> Self {
+		Vec2::new(value.x, value.y)
+	}
+}
+
+impl From<Vec2> for Position<f32> {
+	fn from(value: Vec2) -> Self {
+		Position::new(value.x, value.y)
+	}
+}
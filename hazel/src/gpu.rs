@@ -0,0 +1,435 @@
+use std::{
+	sync::{Arc, Mutex, PoisonError},
+	time::Duration,
+};
+
+use pollster::FutureExt;
+use wgpu::{
+	Adapter, Backends, Buffer, BufferDescriptor, BufferUsages, CommandEncoder,
+	CommandEncoderDescriptor, CompositeAlphaMode, Device, DeviceDescriptor, Extent3d, Features,
+	ImageCopyBuffer, ImageDataLayout, Instance, InstanceDescriptor, Maintain, MapMode, MemoryHints,
+	QuerySet, QuerySetDescriptor, QueryType, Queue, RenderPassTimestampWrites,
+	RequestAdapterOptions, Surface, Texture, TextureDescriptor, TextureDimension, TextureFormat,
+	TextureUsages, TextureView, TextureViewDescriptor, COPY_BYTES_PER_ROW_ALIGNMENT, QUERY_SIZE,
+};
+
+use crate::{
+	core_info, core_warn,
+	geometry::Size,
+	log::{core_log, Level},
+	renderer::RendererConfig,
+	Error, WindowSpec,
+};
+
+pub(crate) const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+/// Runs `connect` with an instance limited to the backends of `spec`, and again with all
+/// backends if the requested ones yield no adapter.
+pub(crate) fn with_backend_fallback<T>(
+	spec: &WindowSpec,
+	connect: impl Fn(Instance) -> Result<T, Error>,
+) -> Result<T, Error> {
+	let create_instance =
+		|backends| Instance::new(InstanceDescriptor { backends, ..InstanceDescriptor::default() });
+	match connect(create_instance(spec.backends)) {
+		Err(Error::NoAdapter) if spec.backends != Backends::all() => {
+			let backends = spec.backends;
+			core_warn!("No adapter found for backends {backends:?}, trying all backends");
+			connect(create_instance(Backends::all()))
+		},
+		result => result,
+	}
+}
+
+/// Requests an adapter matching `spec`, retrying with the software adapter if none is found.
+pub(crate) fn request_adapter(
+	instance: &Instance,
+	spec: &WindowSpec,
+	compatible_surface: Option<&Surface>,
+) -> Result<Adapter, Error> {
+	let request = |force_fallback_adapter| {
+		instance
+			.request_adapter(&RequestAdapterOptions {
+				power_preference: spec.power_preference,
+				compatible_surface,
+				force_fallback_adapter,
+			})
+			.block_on()
+	};
+
+	let adapter = request(spec.force_fallback_adapter)
+		.or_else(|| {
+			if spec.force_fallback_adapter {
+				return None;
+			}
+			core_warn!("No hardware adapter found, falling back to the software adapter");
+			request(true)
+		})
+		.ok_or(Error::NoAdapter)?;
+
+	let info = adapter.get_info();
+	let (name, backend, device_type) = (&info.name, info.backend, info.device_type);
+	core_info!("Using adapter {name} ({backend:?}, {device_type:?})");
+
+	Ok(adapter)
+}
+
+/// Requests a device with the features and limits of `spec`, checking them against the adapter
+/// first so unsupported ones are reported instead of failing inside wgpu.
+pub(crate) fn request_device(
+	adapter: &Adapter,
+	spec: &WindowSpec,
+) -> Result<(Device, Queue), Error> {
+	let missing_features = spec.required_features - adapter.features();
+	if !missing_features.is_empty() {
+		return Err(Error::MissingFeatures(missing_features));
+	}
+
+	let mut unsupported_limits = Vec::new();
+	spec.required_limits.check_limits_with_fail_fn(&adapter.limits(), false, |name, _, _| {
+		unsupported_limits.push(name);
+	});
+	if !unsupported_limits.is_empty() {
+		return Err(Error::UnsupportedLimits(unsupported_limits));
+	}
+
+	let mut required_features = spec.required_features;
+	// timestamps for FrameStats::gpu_frame_time, where the adapter has them
+	if cfg!(feature = "profile") {
+		required_features |= adapter.features() & Features::TIMESTAMP_QUERY;
+	}
+
+	let (device, queue) = adapter
+		.request_device(
+			&DeviceDescriptor {
+				label: None,
+				required_features,
+				required_limits: spec.required_limits.clone(),
+				memory_hints: MemoryHints::default(),
+			},
+			None,
+		)
+		.block_on()?;
+	set_error_handler(&device, spec.panic_on_gpu_error);
+	Ok((device, queue))
+}
+
+/// Logs errors that were not caught with an error scope, such as validation errors in a pipeline
+/// a layer built, instead of the default handler's bare panic. The message includes the labels
+/// of the objects involved. Logged in release builds as well, like panics.
+fn set_error_handler(device: &Device, panic: bool) {
+	device.on_uncaptured_error(Box::new(move |error| {
+		let kind = match error {
+			wgpu::Error::OutOfMemory { .. } => "out of memory",
+			wgpu::Error::Validation { .. } => "validation error",
+			wgpu::Error::Internal { .. } => "internal error",
+		};
+		core_log(Level::Error, format!("GPU {kind}: {error}"));
+		assert!(!panic, "Uncaptured GPU {kind}");
+	}));
+}
+
+/// Picks the surface format: `requested` if the surface supports it, otherwise the first sRGB
+/// format, so that the linear colors layers output are encoded correctly for display.
+pub(crate) fn choose_surface_format(
+	formats: &[TextureFormat],
+	requested: Option<TextureFormat>,
+) -> Option<TextureFormat> {
+	if let Some(requested) = requested {
+		if formats.contains(&requested) {
+			return Some(requested);
+		}
+		core_warn!("Surface format {requested:?} is not supported, choosing one instead");
+	}
+
+	let format = formats.iter().find(|it| it.is_srgb()).or_else(|| formats.first()).copied();
+	if let Some(format) = format.filter(|it| !it.is_srgb()) {
+		core_warn!("No sRGB surface format available, colors will look off with {format:?}");
+	}
+	format
+}
+
+/// Picks an alpha mode that lets the window show through where the frame is transparent, or
+/// keeps the surface default if `transparent` is off or none is supported.
+pub(crate) fn choose_alpha_mode(
+	alpha_modes: &[CompositeAlphaMode],
+	default: CompositeAlphaMode,
+	transparent: bool,
+) -> CompositeAlphaMode {
+	if !transparent {
+		return default;
+	}
+	let preferred = [
+		CompositeAlphaMode::PreMultiplied,
+		CompositeAlphaMode::PostMultiplied,
+		CompositeAlphaMode::Inherit,
+	];
+	let Some(alpha_mode) = preferred.into_iter().find(|it| alpha_modes.contains(it))
+	else {
+		core_warn!("The surface does not support transparency, the window stays opaque");
+		return default;
+	};
+	alpha_mode
+}
+
+/// The sample counts wgpu can create textures with, highest first.
+const SAMPLE_COUNTS: [u32; 4] = [8, 4, 2, 1];
+
+/// Clamps `requested` to the highest sample count that both `format` and, if used, the depth
+/// format support on `adapter`.
+pub(crate) fn supported_sample_count(
+	adapter: &Adapter,
+	format: TextureFormat,
+	depth: bool,
+	requested: u32,
+) -> u32 {
+	let is_supported = |count| {
+		adapter.get_texture_format_features(format).flags.sample_count_supported(count)
+			&& (!depth
+				|| adapter
+					.get_texture_format_features(DEPTH_FORMAT)
+					.flags
+					.sample_count_supported(count))
+	};
+
+	let sample_count = SAMPLE_COUNTS
+		.into_iter()
+		.filter(|&count| count <= requested)
+		.find(|&count| is_supported(count))
+		.unwrap_or(1);
+	if sample_count != requested {
+		core_warn!("Sample count {requested} is not supported, using {sample_count} instead");
+	}
+	sample_count
+}
+
+/// The textures the shared render pass draws into besides the output texture. They have to
+/// match its size, so they are recreated on resize.
+pub(crate) struct RenderTargets {
+	/// Present if [`WindowSpec::depth_compare`] is set.
+	pub(crate) depth: Option<TextureView>,
+	/// The multisampled color target that is resolved into the output texture, present if
+	/// `sample_count` is greater than 1.
+	pub(crate) msaa: Option<TextureView>,
+}
+
+impl RenderTargets {
+	pub(crate) fn new(device: &Device, config: &RendererConfig, size: Size<u32>) -> Self {
+		let RendererConfig { format, sample_count, .. } = *config;
+		let create_view = |label, format| {
+			device
+				.create_texture(&TextureDescriptor {
+					label: Some(label),
+					size: Extent3d {
+						width: size.width,
+						height: size.height,
+						depth_or_array_layers: 1,
+					},
+					mip_level_count: 1,
+					sample_count,
+					dimension: TextureDimension::D2,
+					format,
+					usage: TextureUsages::RENDER_ATTACHMENT,
+					view_formats: &[],
+				})
+				.create_view(&TextureViewDescriptor::default())
+		};
+
+		RenderTargets {
+			depth: config.depth_format().map(|format| create_view("depth buffer", format)),
+			msaa: (sample_count > 1).then(|| create_view("multisampled color target", format)),
+		}
+	}
+}
+
+/// Measures the GPU time of the shared render pass with timestamp queries. Results are read back
+/// without blocking, so they arrive a frame or more late, and frames that end while a readback
+/// is still pending are not measured.
+pub(crate) struct GpuTimer {
+	query_set: QuerySet,
+	resolve_buffer: Buffer,
+	readback_buffer: Buffer,
+	/// Nanoseconds per timestamp tick.
+	period: f32,
+	/// Whether the readback buffer holds timestamps that are being mapped.
+	in_flight: bool,
+	/// Set by the map callback: whether mapping succeeded.
+	map_result: Arc<Mutex<Option<bool>>>,
+}
+
+impl GpuTimer {
+	/// `None` if the device was created without `TIMESTAMP_QUERY`.
+	pub(crate) fn new(device: &Device, queue: &Queue) -> Option<Self> {
+		if !device.features().contains(Features::TIMESTAMP_QUERY) {
+			return None;
+		}
+
+		let create_buffer = |label, usage| {
+			device.create_buffer(&BufferDescriptor {
+				label: Some(label),
+				size: 2 * u64::from(QUERY_SIZE),
+				usage,
+				mapped_at_creation: false,
+			})
+		};
+		Some(GpuTimer {
+			query_set: device.create_query_set(&QuerySetDescriptor {
+				label: Some("render pass timestamps"),
+				ty: QueryType::Timestamp,
+				count: 2,
+			}),
+			resolve_buffer: create_buffer(
+				"timestamp resolve buffer",
+				BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+			),
+			readback_buffer: create_buffer(
+				"timestamp readback buffer",
+				BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+			),
+			period: queue.get_timestamp_period(),
+			in_flight: false,
+			map_result: Arc::default(),
+		})
+	}
+
+	pub(crate) fn timestamp_writes(&self) -> RenderPassTimestampWrites<'_> {
+		RenderPassTimestampWrites {
+			query_set: &self.query_set,
+			beginning_of_pass_write_index: Some(0),
+			end_of_pass_write_index: Some(1),
+		}
+	}
+
+	/// Resolves the timestamps of the pass, and copies them for reading back unless an earlier
+	/// copy is still being read.
+	pub(crate) fn resolve(&self, encoder: &mut CommandEncoder) {
+		encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve_buffer, 0);
+		if !self.in_flight {
+			let size = self.readback_buffer.size();
+			encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &self.readback_buffer, 0, size);
+		}
+	}
+
+	/// Starts reading back the copy made by [`GpuTimer::resolve`], once its commands are
+	/// submitted.
+	pub(crate) fn map_readback(&mut self) {
+		if self.in_flight {
+			return;
+		}
+
+		self.in_flight = true;
+		let map_result = self.map_result.clone();
+		self.readback_buffer.slice(..).map_async(MapMode::Read, move |result| {
+			*map_result.lock().unwrap_or_else(PoisonError::into_inner) = Some(result.is_ok());
+		});
+	}
+
+	/// The time between the two timestamps of the last readback, if it finished since the
+	/// previous call.
+	pub(crate) fn collect(&mut self, device: &Device) -> Option<Duration> {
+		if !self.in_flight {
+			return None;
+		}
+
+		device.poll(Maintain::Poll);
+		let mapped = self.map_result.lock().unwrap_or_else(PoisonError::into_inner).take()?;
+		self.in_flight = false;
+		if !mapped {
+			return None;
+		}
+
+		let ticks = {
+			let data = self.readback_buffer.slice(..).get_mapped_range();
+			let timestamp = |index: usize| {
+				let bytes = data[index * 8..(index + 1) * 8].try_into().expect("8 bytes");
+				u64::from_le_bytes(bytes)
+			};
+			// some drivers report timestamps that run backwards, count those as zero
+			timestamp(1).saturating_sub(timestamp(0))
+		};
+		self.readback_buffer.unmap();
+		Some(Duration::from_nanos(ticks).mul_f32(self.period))
+	}
+}
+
+/// A copy of a 4-byte-per-pixel texture being read back to the CPU, e.g. for a screenshot.
+/// Reading back does not block the frame: poll with [`TextureReadback::try_take`] on later frames,
+/// or block with [`TextureReadback::wait`].
+pub(crate) struct TextureReadback {
+	buffer: Buffer,
+	size: Extent3d,
+	padded_bytes_per_row: u32,
+	/// Set by the map callback: whether mapping succeeded.
+	map_result: Arc<Mutex<Option<bool>>>,
+}
+
+impl TextureReadback {
+	/// Copies the texture in its current state and starts mapping the copy.
+	pub(crate) fn new(device: &Device, queue: &Queue, texture: &Texture) -> Self {
+		let size = texture.size();
+		let padded_bytes_per_row =
+			(size.width * 4).div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+		let buffer = device.create_buffer(&BufferDescriptor {
+			label: Some("readback buffer"),
+			size: u64::from(padded_bytes_per_row) * u64::from(size.height),
+			usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+			mapped_at_creation: false,
+		});
+
+		let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+		encoder.copy_texture_to_buffer(
+			texture.as_image_copy(),
+			ImageCopyBuffer {
+				buffer: &buffer,
+				layout: ImageDataLayout {
+					offset: 0,
+					bytes_per_row: Some(padded_bytes_per_row),
+					rows_per_image: Some(size.height),
+				},
+			},
+			size,
+		);
+		queue.submit(Some(encoder.finish()));
+
+		let map_result: Arc<Mutex<Option<bool>>> = Arc::default();
+		let callback_result = map_result.clone();
+		buffer.slice(..).map_async(MapMode::Read, move |result| {
+			*callback_result.lock().unwrap_or_else(PoisonError::into_inner) = Some(result.is_ok());
+		});
+
+		TextureReadback { buffer, size, padded_bytes_per_row, map_result }
+	}
+
+	#[must_use]
+	pub(crate) fn size(&self) -> Extent3d {
+		self.size
+	}
+
+	/// The pixels, tightly packed, if the readback finished. Polls the device without blocking.
+	pub(crate) fn try_take(&self, device: &Device) -> Option<Result<Vec<u8>, Error>> {
+		device.poll(Maintain::Poll);
+		let mapped = self.map_result.lock().unwrap_or_else(PoisonError::into_inner).take()?;
+		if !mapped {
+			return Some(Err(Error::Unknown("Could not map the readback buffer".into())));
+		}
+
+		let unpadded_bytes_per_row = self.size.width as usize * 4;
+		let pixels = self
+			.buffer
+			.slice(..)
+			.get_mapped_range()
+			.chunks_exact(self.padded_bytes_per_row as usize)
+			.flat_map(|row| &row[..unpadded_bytes_per_row])
+			.copied()
+			.collect();
+		self.buffer.unmap();
+		Some(Ok(pixels))
+	}
+
+	/// Blocks until the readback finished.
+	pub(crate) fn wait(self, device: &Device) -> Result<Vec<u8>, Error> {
+		device.poll(Maintain::Wait);
+		self.try_take(device)
+			.unwrap_or_else(|| Err(Error::Unknown("The readback did not finish".into())))
+	}
+}
@@ -0,0 +1,536 @@
+use std::time::Duration;
+
+use wgpu::{
+	Adapter, Device, Extent3d, Queue, Texture, TextureDescriptor, TextureDimension, TextureFormat,
+	TextureUsages, TextureView, TextureViewDescriptor,
+};
+
+use crate::{
+	assets::AssetManager,
+	context::LayerContext,
+	event::Event,
+	geometry::Size,
+	gpu,
+	gpu::{GpuTimer, RenderTargets, TextureReadback},
+	layer::LayerStack,
+	render_layers,
+	renderer::RendererConfig,
+	screenshot::Screenshots,
+	time::FixedTimestep,
+	update_layers, Application, Context, Error, ExitReason, WindowSpec,
+};
+
+/// Builds the [`LayerContext`] of a headless frame from the fields of a [`Harness`].
+macro_rules! headless_layer_context {
+	($harness:expr) => {
+		LayerContext {
+			adapter: &$harness.adapter,
+			device: &$harness.device,
+			queue: &$harness.queue,
+			renderer_config: $harness.renderer_config,
+			size: $harness.size,
+			scale_factor: 1.0,
+			window: None,
+			frame_stats: &$harness.context.frame_stats,
+			render_settings: &mut $harness.context.render_settings,
+			input: &$harness.context.input,
+			assets: &mut $harness.assets,
+			#[cfg(feature = "audio")]
+			audio: &mut $harness.context.audio,
+			replay: &mut $harness.context.replay,
+			layer_timings: &$harness.context.layer_timings,
+			exit_reason: &mut $harness.context.exit_reason,
+			redraw_deadline: &mut $harness.context.redraw_deadline,
+			event_queue: &$harness.context.event_queue,
+			screenshot_path: &mut $harness.context.screenshot_path,
+			fixed_timestep: $harness.context.fixed_timestep.map(FixedTimestep::step),
+			interpolation_alpha: $harness.context.fixed_timestep.map_or(1.0, FixedTimestep::alpha),
+			#[cfg(feature = "net")]
+			remote_events: &mut $harness.context.remote_events,
+			surface: None,
+		}
+	};
+}
+
+/// Runs the layer stack for `frames` frames against an offscreen texture of the given `size`
+/// instead of a window, and returns the final frame as tightly packed RGBA8 (sRGB) pixels.
+///
+/// No event loop is created, so layers only see `on_update` and `on_render`. See [`Harness`] to
+/// drive the frames one by one instead.
+///
+/// # Errors
+/// Fails if `size` is empty or `frames` is zero, or if no adapter or device is available.
+/// `frames` must be at least one so every `on_start` is paired with an `on_stop`.
+pub fn run_headless<App: Application>(
+	app: App,
+	layer_setup: impl Fn(&mut LayerStack),
+	size: Size<u32>,
+	frames: u32,
+) -> Result<Vec<u8>, Error> {
+	if frames == 0 {
+		return Err(Error::Unknown("Headless runs need at least one frame".into()));
+	}
+
+	let mut harness = Harness::new(app, layer_setup, size)?;
+	for _ in 0..frames {
+		harness.frame(None);
+		if harness.is_exit_requested() {
+			break;
+		}
+	}
+	harness.stop();
+	harness.read_pixels()
+}
+
+/// Drives an application and its layers frame by frame against an offscreen texture, e.g. to
+/// test layers without a window.
+///
+/// Frames advance by the delta time passed to [`Harness::tick`] rather than by the clock, so runs
+/// are deterministic. Events passed to [`Harness::apply_input`], or posted to the
+/// [`EventQueue`](crate::EventQueue), update the input state layers read through
+/// [`LayerContext::input`]. They are not dispatched: the application callbacks and
+/// [`Layer::on_event`](crate::layer::Layer::on_event) need a running event loop.
+///
+/// `on_start` runs before the first frame and `on_stop` in [`Harness::stop`], or when the harness
+/// is dropped.
+pub struct Harness<App: Application> {
+	context: Context<'static, App>,
+	adapter: Adapter,
+	device: Device,
+	queue: Queue,
+	texture: Texture,
+	view: TextureView,
+	size: Size<u32>,
+	renderer_config: RendererConfig,
+	targets: RenderTargets,
+	assets: AssetManager,
+	gpu_timer: Option<GpuTimer>,
+	screenshots: Screenshots,
+	/// Events a `NetLayer` received during the last frame.
+	#[cfg(feature = "net")]
+	remote_events: Vec<Event>,
+	started: bool,
+	stopped: bool,
+}
+
+impl<App: Application> Harness<App> {
+	/// # Errors
+	/// Fails if `size` is empty, or if no adapter or device is available.
+	pub fn new(
+		app: App,
+		layer_setup: impl Fn(&mut LayerStack),
+		size: Size<u32>,
+	) -> Result<Self, Error> {
+		Harness::with_spec(app, WindowSpec::default(), layer_setup, size)
+	}
+
+	/// A harness using the GPU and update settings of `spec`, e.g. its
+	/// [`WindowSpec::fixed_timestep`]. Window settings do not apply.
+	///
+	/// # Errors
+	/// Fails if `size` is empty, or if no adapter or device is available.
+	pub fn with_spec(
+		app: App,
+		spec: WindowSpec,
+		layer_setup: impl Fn(&mut LayerStack),
+		size: Size<u32>,
+	) -> Result<Self, Error> {
+		if size.width == 0 || size.height == 0 {
+			return Err(Error::Unknown(format!("Invalid headless size {size:?}")));
+		}
+
+		let context = Context::new(app, spec, layer_setup);
+
+		let adapter = gpu::with_backend_fallback(&context.spec, |instance| {
+			gpu::request_adapter(&instance, &context.spec, None)
+		})?;
+		let (device, queue) = gpu::request_device(&adapter, &context.spec)?;
+
+		let format = TextureFormat::Rgba8UnormSrgb;
+		let texture = device.create_texture(&TextureDescriptor {
+			label: Some("headless target"),
+			size: Extent3d { width: size.width, height: size.height, depth_or_array_layers: 1 },
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: TextureDimension::D2,
+			format,
+			usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+			view_formats: &[],
+		});
+		let view = texture.create_view(&TextureViewDescriptor::default());
+		let sample_count = gpu::supported_sample_count(
+			&adapter,
+			format,
+			context.spec.depth_compare.is_some(),
+			context.spec.sample_count,
+		);
+		let renderer_config =
+			RendererConfig { format, sample_count, depth_compare: context.spec.depth_compare };
+		let targets = RenderTargets::new(&device, &renderer_config, size);
+		let assets = AssetManager::new(&device, &queue);
+		let gpu_timer = GpuTimer::new(&device, &queue);
+
+		Ok(Harness {
+			context,
+			adapter,
+			device,
+			queue,
+			texture,
+			view,
+			size,
+			renderer_config,
+			targets,
+			assets,
+			gpu_timer,
+			screenshots: Screenshots::default(),
+			#[cfg(feature = "net")]
+			remote_events: Vec::new(),
+			started: false,
+			stopped: false,
+		})
+	}
+
+	/// Applies an event to the input state, as seen by layers from the next frame on. Events
+	/// posted to the [`EventQueue`](crate::event::EventQueue) are applied the same way.
+	///
+	/// The event is not dispatched to the application callbacks or to
+	/// [`Layer::on_event`](crate::layer::Layer::on_event), which need a running event loop.
+	pub fn apply_input(&mut self, event: &Event) {
+		self.context.input.handle(event);
+	}
+
+	/// Runs one frame, updates and render, as if `delta_time` passed since the previous one.
+	pub fn tick(&mut self, delta_time: Duration) {
+		self.frame(Some(delta_time));
+	}
+
+	/// Runs one frame, timed by the clock if `delta_time` is `None`.
+	fn frame(&mut self, delta_time: Option<Duration>) {
+		match delta_time {
+			Some(delta_time) => self.context.frame_stats.push_frame_time(delta_time),
+			None => self.context.frame_stats.begin_frame(),
+		}
+		if let Some(gpu_time) = self.gpu_timer.as_mut().and_then(|it| it.collect(&self.device)) {
+			self.context.frame_stats.set_gpu_frame_time(gpu_time);
+		}
+		self.screenshots.save_finished(&self.device);
+		while let Ok(event) = self.context.posted_events.try_recv() {
+			self.apply_input(&event);
+		}
+		self.context.layer_timings = self.context.layer_stack.take_timings();
+		self.assets.upload_decoded(&self.device, &self.queue);
+
+		if !self.started {
+			self.started = true;
+			self.context.application.on_start(&mut headless_layer_context!(self));
+		}
+		let frame_time = self.context.frame_stats.delta_time().duration();
+		let steps = self.context.fixed_timestep.as_mut().map_or(1, |it| it.advance(frame_time));
+		for _ in 0..steps {
+			update_layers(&mut self.context.layer_stack, &mut headless_layer_context!(self));
+			self.context.input.end_update();
+		}
+		#[cfg(feature = "net")]
+		{
+			self.remote_events = std::mem::take(&mut self.context.remote_events);
+		}
+
+		let layer_context = headless_layer_context!(self);
+		let command_buffer = render_layers(
+			&mut self.context.layer_stack,
+			&layer_context,
+			&self.view,
+			&self.targets,
+			self.gpu_timer.as_ref(),
+		);
+		self.queue.submit(Some(command_buffer));
+		if let Some(gpu_timer) = &mut self.gpu_timer {
+			gpu_timer.map_readback();
+		}
+		if let Some(path) = self.context.screenshot_path.take() {
+			self.screenshots.capture(&self.device, &self.queue, &self.texture, path);
+		}
+	}
+
+	/// The events a peer sent through a [`NetLayer`](crate::net::NetLayer) that arrived during the
+	/// last frame.
+	///
+	/// They take the place of [`Application::on_remote_event`], which needs a running event loop.
+	#[cfg(feature = "net")]
+	#[must_use]
+	pub fn remote_events(&self) -> &[Event] {
+		&self.remote_events
+	}
+
+	/// Whether a layer called [`LayerContext::request_exit`].
+	#[must_use]
+	pub fn is_exit_requested(&self) -> bool {
+		self.context.exit_reason.is_some()
+	}
+
+	/// Why the application would exit, recorded when the exit was requested.
+	#[must_use]
+	pub fn exit_reason(&self) -> Option<ExitReason> {
+		self.context.exit_reason
+	}
+
+	/// Calls `on_stop`, if `on_start` ran and `on_stop` did not yet.
+	pub fn stop(&mut self) {
+		if !self.started || self.stopped {
+			return;
+		}
+
+		self.stopped = true;
+		let mut layer_context = headless_layer_context!(self);
+		self.context.application.on_stop(&mut layer_context);
+	}
+
+	#[must_use]
+	pub fn application(&self) -> &App {
+		&self.context.application
+	}
+
+	#[must_use]
+	pub fn application_mut(&mut self) -> &mut App {
+		&mut self.context.application
+	}
+
+	#[must_use]
+	pub fn layer_stack(&self) -> &LayerStack {
+		&self.context.layer_stack
+	}
+
+	/// The layer stack, e.g. to enable or disable layers between frames.
+	#[must_use]
+	pub fn layer_stack_mut(&mut self) -> &mut LayerStack {
+		&mut self.context.layer_stack
+	}
+
+	/// The last rendered frame as tightly packed RGBA8 (sRGB) pixels.
+	///
+	/// # Errors
+	/// Fails if the texture cannot be read back.
+	pub fn read_pixels(&self) -> Result<Vec<u8>, Error> {
+		TextureReadback::new(&self.device, &self.queue, &self.texture).wait(&self.device)
+	}
+}
+
+impl<App: Application> Drop for Harness<App> {
+	fn drop(&mut self) {
+		self.stop();
+		self.screenshots.save_all(&self.device);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{cell::RefCell, rc::Rc, time::Duration};
+
+	use super::{run_headless, Harness};
+	use crate::{
+		context::LayerContext, event::Event, geometry::Size, layer::Layer, Error, ExitReason,
+		KeyCode, WindowSpec,
+	};
+
+	/// Records the delta time and whether `A` was just pressed, per update.
+	struct Probe(Rc<RefCell<Vec<(Duration, bool)>>>);
+
+	impl Layer for Probe {
+		fn name(&self) -> &'static str {
+			"Probe"
+		}
+
+		fn on_update(&mut self, context: &mut LayerContext) {
+			let just_pressed = context.input.is_key_just_pressed(KeyCode::A);
+			self.0.borrow_mut().push((context.delta_time().duration(), just_pressed));
+		}
+	}
+
+	#[test]
+	fn tick_steps_layers_with_applied_input() {
+		let updates = Rc::new(RefCell::new(Vec::new()));
+		let harness = Harness::new(
+			(),
+			|layer_stack| {
+				layer_stack.push_layer(Probe(Rc::clone(&updates)));
+			},
+			Size::new(4, 4),
+		);
+		let mut harness = match harness {
+			Ok(harness) => harness,
+			Err(Error::NoAdapter) => return,
+			Err(error) => panic!("{error:?}"),
+		};
+
+		harness.apply_input(&Event::KeyPressed {
+			key: KeyCode::A,
+			physical: KeyCode::A,
+			is_repeat: false,
+		});
+		harness.tick(Duration::from_millis(16));
+		harness.tick(Duration::from_millis(20));
+
+		assert_eq!(*updates.borrow(), [
+			(Duration::from_millis(16), true),
+			(Duration::from_millis(20), false)
+		]);
+		assert_eq!(harness.read_pixels().map(|it| it.len()).ok(), Some(4 * 4 * 4));
+	}
+
+	/// Requests an exit in its first update, and counts its updates.
+	struct Quitter(Rc<RefCell<u32>>);
+
+	impl Layer for Quitter {
+		fn name(&self) -> &'static str {
+			"Quitter"
+		}
+
+		fn on_update(&mut self, context: &mut LayerContext) {
+			*self.0.borrow_mut() += 1;
+			context.request_exit();
+		}
+	}
+
+	#[test]
+	fn exit_requests_end_the_run_after_the_frame() {
+		let updates = Rc::new(RefCell::new(0));
+		let result = run_headless(
+			(),
+			|layer_stack| {
+				layer_stack.push_layer(Quitter(Rc::clone(&updates)));
+			},
+			Size::new(1, 1),
+			5,
+		);
+		match result {
+			Ok(_) => assert_eq!(*updates.borrow(), 1),
+			Err(Error::NoAdapter) => return,
+			Err(error) => panic!("{error:?}"),
+		}
+
+		let mut harness = Harness::new(
+			(),
+			|layer_stack| {
+				layer_stack.push_layer(Quitter(Rc::clone(&updates)));
+			},
+			Size::new(1, 1),
+		)
+		.unwrap();
+		assert_eq!(harness.exit_reason(), None);
+		harness.tick(Duration::from_millis(16));
+		assert_eq!(harness.exit_reason(), Some(ExitReason::LayerRequested));
+	}
+
+	#[test]
+	fn only_the_first_fixed_step_sees_a_press() {
+		let step = Duration::from_millis(10);
+		let updates = Rc::new(RefCell::new(Vec::new()));
+		let spec = WindowSpec { fixed_timestep: Some(step), ..WindowSpec::default() };
+		let harness = Harness::with_spec(
+			(),
+			spec,
+			|layer_stack| {
+				layer_stack.push_layer(Probe(Rc::clone(&updates)));
+			},
+			Size::new(1, 1),
+		);
+		let mut harness = match harness {
+			Ok(harness) => harness,
+			Err(Error::NoAdapter) => return,
+			Err(error) => panic!("{error:?}"),
+		};
+
+		// a press in a frame without a step waits for the next step
+		harness.apply_input(&Event::KeyPressed {
+			key: KeyCode::A,
+			physical: KeyCode::A,
+			is_repeat: false,
+		});
+		harness.tick(Duration::from_millis(4));
+		assert!(updates.borrow().is_empty());
+
+		// a long frame catches up with several steps
+		harness.tick(Duration::from_millis(31));
+		assert_eq!(*updates.borrow(), [(step, true), (step, false), (step, false)]);
+	}
+
+	/// Posts a press of `A` in its first update, and records whether `A` was just pressed.
+	struct Poster(Rc<RefCell<Vec<bool>>>);
+
+	impl Layer for Poster {
+		fn name(&self) -> &'static str {
+			"Poster"
+		}
+
+		fn on_update(&mut self, context: &mut LayerContext) {
+			let mut updates = self.0.borrow_mut();
+			if updates.is_empty() {
+				context.event_queue().post(Event::KeyPressed {
+					key: KeyCode::A,
+					physical: KeyCode::A,
+					is_repeat: false,
+				});
+			}
+			updates.push(context.input.is_key_just_pressed(KeyCode::A));
+		}
+	}
+
+	#[test]
+	fn posted_events_reach_the_input_of_the_next_frame() {
+		let updates = Rc::new(RefCell::new(Vec::new()));
+		let harness = Harness::new(
+			(),
+			|layer_stack| {
+				layer_stack.push_layer(Poster(Rc::clone(&updates)));
+			},
+			Size::new(1, 1),
+		);
+		let mut harness = match harness {
+			Ok(harness) => harness,
+			Err(Error::NoAdapter) => return,
+			Err(error) => panic!("{error:?}"),
+		};
+
+		for _ in 0..3 {
+			harness.tick(Duration::from_millis(16));
+		}
+		assert_eq!(*updates.borrow(), [false, true, false]);
+	}
+
+	/// Stands in for a `NetLayer` receiving `WindowClose` from a peer in every update.
+	#[cfg(feature = "net")]
+	struct Peer;
+
+	#[cfg(feature = "net")]
+	impl Layer for Peer {
+		fn name(&self) -> &'static str {
+			"Peer"
+		}
+
+		fn on_update(&mut self, context: &mut LayerContext) {
+			context.remote_events.push(Event::WindowClose);
+		}
+	}
+
+	#[cfg(feature = "net")]
+	#[test]
+	fn remote_events_are_kept_for_one_frame() {
+		let harness = Harness::new(
+			(),
+			|layer_stack| {
+				layer_stack.push_layer(Peer);
+			},
+			Size::new(1, 1),
+		);
+		let mut harness = match harness {
+			Ok(harness) => harness,
+			Err(Error::NoAdapter) => return,
+			Err(error) => panic!("{error:?}"),
+		};
+
+		assert!(harness.remote_events().is_empty());
+		harness.tick(Duration::from_millis(16));
+		harness.tick(Duration::from_millis(16));
+		assert_eq!(harness.remote_events(), [Event::WindowClose]);
+	}
+}
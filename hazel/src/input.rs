@@ -0,0 +1,394 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{event::Event, geometry::Position, KeyCode, MouseButton};
+
+/// The state of the keyboard modifier keys.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Modifiers {
+	pub ctrl: bool,
+	pub shift: bool,
+	pub alt: bool,
+	pub super_key: bool,
+}
+
+impl From<winit::keyboard::ModifiersState> for Modifiers {
+	fn from(value: winit::keyboard::ModifiersState) -> Self {
+		Modifiers {
+			ctrl: value.control_key(),
+			shift: value.shift_key(),
+			alt: value.alt_key(),
+			super_key: value.super_key(),
+		}
+	}
+}
+
+/// Input state accumulated from the events dispatched so far.
+#[derive(Default)]
+pub struct Input {
+	keys: HashSet<KeyCode>,
+	/// Keys that went down or up since the last update, without auto-repeat.
+	keys_pressed: HashSet<KeyCode>,
+	keys_released: HashSet<KeyCode>,
+	/// The same for physical keys.
+	physical_keys: HashSet<KeyCode>,
+	physical_keys_pressed: HashSet<KeyCode>,
+	physical_keys_released: HashSet<KeyCode>,
+	/// The logical key each held physical key was pressed as, which can differ from the logical
+	/// key it is released as, e.g. Shift+1 pressed as `!` and released as `1` after Shift.
+	held_logical_keys: HashMap<KeyCode, KeyCode>,
+	mouse_buttons: HashSet<MouseButton>,
+	/// Mouse buttons that went down or up since the last update.
+	mouse_buttons_pressed: HashSet<MouseButton>,
+	mouse_buttons_released: HashSet<MouseButton>,
+	mouse_position: Position<f32>,
+	modifiers: Modifiers,
+}
+
+impl Input {
+	pub(crate) fn handle(&mut self, event: &Event) {
+		match event {
+			Event::KeyPressed { key, physical, is_repeat } => {
+				if !is_repeat {
+					self.keys_pressed.insert(*key);
+					self.physical_keys_pressed.insert(*physical);
+				}
+				self.keys.insert(*key);
+				self.physical_keys.insert(*physical);
+				if *physical != KeyCode::Unknown {
+					if let Some(previous) = self.held_logical_keys.insert(*physical, *key) {
+						// the layout or modifiers changed between repeats
+						if previous != *key {
+							self.keys.remove(&previous);
+						}
+					}
+				}
+			},
+			Event::KeyReleased { key, physical } => {
+				let held = self.held_logical_keys.remove(physical).unwrap_or(*key);
+				self.keys_released.insert(held);
+				self.keys.remove(&held);
+				self.physical_keys_released.insert(*physical);
+				self.physical_keys.remove(physical);
+			},
+			Event::MouseButtonPressed(button) => {
+				self.mouse_buttons_pressed.insert(*button);
+				self.mouse_buttons.insert(*button);
+			},
+			Event::MouseButtonReleased(button) => {
+				self.mouse_buttons_released.insert(*button);
+				self.mouse_buttons.remove(button);
+			},
+			Event::MouseMoved { x, y } => {
+				self.mouse_position = Position::new(*x, *y);
+			},
+			Event::ModifiersChanged(modifiers) => {
+				self.modifiers = *modifiers;
+			},
+			_ => {},
+		}
+	}
+
+	/// Releases everything, e.g. when the window loses focus and release events would be missed.
+	pub(crate) fn reset(&mut self) {
+		self.keys.clear();
+		self.physical_keys.clear();
+		self.held_logical_keys.clear();
+		self.end_update();
+		self.mouse_buttons.clear();
+		self.modifiers = Modifiers::default();
+	}
+
+	/// Starts collecting the keys for the next `is_key_just_*` queries, after layers have updated.
+	pub(crate) fn end_update(&mut self) {
+		self.keys_pressed.clear();
+		self.keys_released.clear();
+		self.physical_keys_pressed.clear();
+		self.physical_keys_released.clear();
+		self.mouse_buttons_pressed.clear();
+		self.mouse_buttons_released.clear();
+	}
+
+	#[must_use]
+	pub fn is_key_pressed(&self, key: KeyCode) -> bool {
+		self.keys.contains(&key)
+	}
+
+	/// Whether the key went down since the previous update. Auto-repeat is ignored, so this is
+	/// true for one update per physical press.
+	#[must_use]
+	pub fn is_key_just_pressed(&self, key: KeyCode) -> bool {
+		self.keys_pressed.contains(&key)
+	}
+
+	/// Whether the key went up since the previous update.
+	#[must_use]
+	pub fn is_key_just_released(&self, key: KeyCode) -> bool {
+		self.keys_released.contains(&key)
+	}
+
+	/// Whether the key at this position on a US layout is held down, whatever the actual layout.
+	#[must_use]
+	pub fn is_physical_key_pressed(&self, key: KeyCode) -> bool {
+		self.physical_keys.contains(&key)
+	}
+
+	/// Whether the key at this position went down since the previous update, see
+	/// [`Input::is_key_just_pressed`].
+	#[must_use]
+	pub fn is_physical_key_just_pressed(&self, key: KeyCode) -> bool {
+		self.physical_keys_pressed.contains(&key)
+	}
+
+	/// Whether the key at this position went up since the previous update.
+	#[must_use]
+	pub fn is_physical_key_just_released(&self, key: KeyCode) -> bool {
+		self.physical_keys_released.contains(&key)
+	}
+
+	/// Also tracks buttons beyond the named ones, e.g. `MouseButton::Other(code)`.
+	#[must_use]
+	pub fn is_mouse_button_pressed(&self, button: MouseButton) -> bool {
+		self.mouse_buttons.contains(&button)
+	}
+
+	/// Whether the button went down since the previous update.
+	#[must_use]
+	pub fn is_mouse_button_just_pressed(&self, button: MouseButton) -> bool {
+		self.mouse_buttons_pressed.contains(&button)
+	}
+
+	/// Whether the button went up since the previous update.
+	#[must_use]
+	pub fn is_mouse_button_just_released(&self, button: MouseButton) -> bool {
+		self.mouse_buttons_released.contains(&button)
+	}
+
+	/// The cursor position in physical pixels relative to the top-left of the window.
+	#[must_use]
+	pub fn mouse_position(&self) -> Position<f32> {
+		self.mouse_position
+	}
+
+	#[must_use]
+	pub fn modifiers(&self) -> Modifiers {
+		self.modifiers
+	}
+
+	#[must_use]
+	pub fn is_ctrl(&self) -> bool {
+		self.modifiers.ctrl
+	}
+
+	#[must_use]
+	pub fn is_shift(&self) -> bool {
+		self.modifiers.shift
+	}
+
+	#[must_use]
+	pub fn is_alt(&self) -> bool {
+		self.modifiers.alt
+	}
+
+	#[must_use]
+	pub fn is_super(&self) -> bool {
+		self.modifiers.super_key
+	}
+}
+
+/// A key or mouse button an action is bound to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+	/// A logical key, following the keyboard layout.
+	Key(KeyCode),
+	/// A key by its position, see [`Input::is_physical_key_pressed`].
+	PhysicalKey(KeyCode),
+	Mouse(MouseButton),
+}
+
+impl From<KeyCode> for Binding {
+	fn from(value: KeyCode) -> Self {
+		Binding::Key(value)
+	}
+}
+
+impl From<MouseButton> for Binding {
+	fn from(value: MouseButton) -> Self {
+		Binding::Mouse(value)
+	}
+}
+
+/// Named actions, like `"jump"` or `"fire"`, bound to any number of keys and mouse buttons, so
+/// layers query what the player wants to do rather than which key they pressed. Bindings can be
+/// changed at any time, e.g. from a settings menu.
+#[derive(Clone, Debug, Default)]
+pub struct ActionMap {
+	bindings: HashMap<String, Vec<Binding>>,
+}
+
+impl ActionMap {
+	#[must_use]
+	pub fn new() -> Self {
+		ActionMap::default()
+	}
+
+	/// Adds a binding to the action, keeping its existing ones.
+	pub fn bind(&mut self, action: impl Into<String>, binding: impl Into<Binding>) {
+		let binding = binding.into();
+		let bindings = self.bindings.entry(action.into()).or_default();
+		if !bindings.contains(&binding) {
+			bindings.push(binding);
+		}
+	}
+
+	/// Removes a binding from the action. Returns `false` if it was not bound.
+	pub fn unbind(&mut self, action: &str, binding: impl Into<Binding>) -> bool {
+		let binding = binding.into();
+		let Some(bindings) = self.bindings.get_mut(action)
+		else {
+			return false;
+		};
+		let len = bindings.len();
+		bindings.retain(|it| *it != binding);
+		bindings.len() != len
+	}
+
+	/// Removes all bindings of the action.
+	pub fn clear(&mut self, action: &str) {
+		self.bindings.remove(action);
+	}
+
+	#[must_use]
+	pub fn bindings(&self, action: &str) -> &[Binding] {
+		self.bindings.get(action).map_or(&[], Vec::as_slice)
+	}
+
+	/// Whether any binding of the action is held down.
+	#[must_use]
+	pub fn is_action_pressed(&self, input: &Input, action: &str) -> bool {
+		self.bindings(action).iter().any(|binding| match *binding {
+			Binding::Key(key) => input.is_key_pressed(key),
+			Binding::PhysicalKey(key) => input.is_physical_key_pressed(key),
+			Binding::Mouse(button) => input.is_mouse_button_pressed(button),
+		})
+	}
+
+	/// Whether any binding of the action went down since the previous update.
+	#[must_use]
+	pub fn action_just_pressed(&self, input: &Input, action: &str) -> bool {
+		self.bindings(action).iter().any(|binding| match *binding {
+			Binding::Key(key) => input.is_key_just_pressed(key),
+			Binding::PhysicalKey(key) => input.is_physical_key_just_pressed(key),
+			Binding::Mouse(button) => input.is_mouse_button_just_pressed(button),
+		})
+	}
+
+	/// Whether any binding of the action went up since the previous update.
+	#[must_use]
+	pub fn action_just_released(&self, input: &Input, action: &str) -> bool {
+		self.bindings(action).iter().any(|binding| match *binding {
+			Binding::Key(key) => input.is_key_just_released(key),
+			Binding::PhysicalKey(key) => input.is_physical_key_just_released(key),
+			Binding::Mouse(button) => input.is_mouse_button_just_released(button),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ActionMap, Binding, Input};
+	use crate::{event::Event, KeyCode, MouseButton};
+
+	fn press(input: &mut Input, key: KeyCode, physical: KeyCode) {
+		input.handle(&Event::KeyPressed { key, physical, is_repeat: false });
+	}
+
+	fn release(input: &mut Input, key: KeyCode, physical: KeyCode) {
+		input.handle(&Event::KeyReleased { key, physical });
+	}
+
+	#[test]
+	fn keys_release_as_pressed() {
+		let mut input = Input::default();
+
+		// Shift+1 types `!`, but releasing Shift first releases the key as `1`
+		press(&mut input, KeyCode::Character('!'), KeyCode::Digit1);
+		assert!(input.is_key_pressed(KeyCode::Character('!')));
+		release(&mut input, KeyCode::Digit1, KeyCode::Digit1);
+		assert!(!input.is_key_pressed(KeyCode::Character('!')));
+		assert!(!input.is_key_pressed(KeyCode::Digit1));
+		assert!(!input.is_physical_key_pressed(KeyCode::Digit1));
+	}
+
+	#[test]
+	fn releases_last_one_update() {
+		let mut input = Input::default();
+		press(&mut input, KeyCode::Character('!'), KeyCode::Digit1);
+		input.handle(&Event::MouseButtonPressed(MouseButton::Left));
+		input.end_update();
+
+		release(&mut input, KeyCode::Digit1, KeyCode::Digit1);
+		input.handle(&Event::MouseButtonReleased(MouseButton::Left));
+		assert!(input.is_key_just_released(KeyCode::Character('!')));
+		assert!(!input.is_key_just_released(KeyCode::Digit1));
+		assert!(input.is_physical_key_just_released(KeyCode::Digit1));
+		assert!(input.is_mouse_button_just_released(MouseButton::Left));
+
+		input.end_update();
+		assert!(!input.is_key_just_released(KeyCode::Character('!')));
+		assert!(!input.is_physical_key_just_released(KeyCode::Digit1));
+		assert!(!input.is_mouse_button_just_released(MouseButton::Left));
+	}
+
+	#[test]
+	fn actions_are_just_released_by_any_binding() {
+		let mut actions = ActionMap::new();
+		actions.bind("jump", KeyCode::Space);
+		actions.bind("fire", MouseButton::Left);
+		let mut input = Input::default();
+
+		press(&mut input, KeyCode::Space, KeyCode::Space);
+		input.handle(&Event::MouseButtonPressed(MouseButton::Left));
+		assert!(!actions.action_just_released(&input, "jump"));
+		input.end_update();
+
+		release(&mut input, KeyCode::Space, KeyCode::Space);
+		assert!(actions.action_just_released(&input, "jump"));
+		assert!(!actions.action_just_released(&input, "fire"));
+		input.handle(&Event::MouseButtonReleased(MouseButton::Left));
+		assert!(actions.action_just_released(&input, "fire"));
+		assert!(!actions.action_just_released(&input, "unbound"));
+		assert_eq!(actions.bindings("fire"), [Binding::Mouse(MouseButton::Left)]);
+	}
+
+	#[test]
+	fn physical_bindings_follow_the_key_position() {
+		let mut actions = ActionMap::new();
+		actions.bind("forward", Binding::PhysicalKey(KeyCode::W));
+		let mut input = Input::default();
+
+		// the key labeled Z on AZERTY sits where W is on QWERTY
+		press(&mut input, KeyCode::Z, KeyCode::W);
+		assert!(actions.action_just_pressed(&input, "forward"));
+		assert!(actions.is_action_pressed(&input, "forward"));
+		assert!(!input.is_key_pressed(KeyCode::W));
+		input.end_update();
+
+		release(&mut input, KeyCode::Z, KeyCode::W);
+		assert!(actions.action_just_released(&input, "forward"));
+		assert!(!actions.is_action_pressed(&input, "forward"));
+		assert!(!input.is_key_just_released(KeyCode::W));
+	}
+
+	#[test]
+	fn unknown_physical_keys_release_by_logical_key() {
+		let mut input = Input::default();
+
+		press(&mut input, KeyCode::Character('ä'), KeyCode::Unknown);
+		press(&mut input, KeyCode::Character('ö'), KeyCode::Unknown);
+		release(&mut input, KeyCode::Character('ä'), KeyCode::Unknown);
+		assert!(!input.is_key_pressed(KeyCode::Character('ä')));
+		assert!(input.is_key_pressed(KeyCode::Character('ö')));
+	}
+}
@@ -0,0 +1,514 @@
+use std::{
+	collections::HashSet,
+	fmt::{self, Display},
+	sync::{LazyLock, Mutex, PoisonError},
+};
+
+use winit::keyboard::{self, Key, KeyLocation, NamedKey, PhysicalKey};
+
+use crate::core_trace;
+
+/// A key, independent of the windowing library.
+///
+/// Keys are logical: they follow the keyboard layout, so the key labeled Z on a German keyboard
+/// is [`KeyCode::Z`]. Letters are case-insensitive. Printable keys without a variant of their own
+/// are reported as [`KeyCode::Character`].
+///
+/// Key events also carry the physical key, the position of the key on the keyboard regardless of
+/// layout, named after the key at that position on a US QWERTY keyboard. Bind movement like WASD
+/// to physical keys so it stays in place on AZERTY, and shortcuts like Ctrl+Z to logical keys so
+/// they match the label.
+///
+/// Keys on the numeric keypad have variants of their own, such as [`KeyCode::Numpad1`], rather
+/// than reporting as their counterparts on the main keyboard. With Num Lock off they report as
+/// the navigation keys printed on them instead.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyCode {
+	A,
+	B,
+	C,
+	D,
+	E,
+	F,
+	G,
+	H,
+	I,
+	J,
+	K,
+	L,
+	M,
+	N,
+	O,
+	P,
+	Q,
+	R,
+	S,
+	T,
+	U,
+	V,
+	W,
+	X,
+	Y,
+	Z,
+	Digit0,
+	Digit1,
+	Digit2,
+	Digit3,
+	Digit4,
+	Digit5,
+	Digit6,
+	Digit7,
+	Digit8,
+	Digit9,
+	F1,
+	F2,
+	F3,
+	F4,
+	F5,
+	F6,
+	F7,
+	F8,
+	F9,
+	F10,
+	F11,
+	F12,
+	Space,
+	Enter,
+	Escape,
+	Tab,
+	Backspace,
+	Delete,
+	Insert,
+	Home,
+	End,
+	PageUp,
+	PageDown,
+	ArrowUp,
+	ArrowDown,
+	ArrowLeft,
+	ArrowRight,
+	Shift,
+	Control,
+	Alt,
+	Super,
+	CapsLock,
+	NumLock,
+	ScrollLock,
+	PrintScreen,
+	Pause,
+	ContextMenu,
+	Numpad0,
+	Numpad1,
+	Numpad2,
+	Numpad3,
+	Numpad4,
+	Numpad5,
+	Numpad6,
+	Numpad7,
+	Numpad8,
+	Numpad9,
+	NumpadAdd,
+	NumpadSubtract,
+	NumpadMultiply,
+	NumpadDivide,
+	NumpadDecimal,
+	NumpadEqual,
+	NumpadEnter,
+	AudioVolumeUp,
+	AudioVolumeDown,
+	AudioVolumeMute,
+	MediaPlayPause,
+	MediaStop,
+	MediaTrackNext,
+	MediaTrackPrevious,
+	Character(char),
+	Unknown,
+}
+
+const LETTERS: [KeyCode; 26] = [
+	KeyCode::A,
+	KeyCode::B,
+	KeyCode::C,
+	KeyCode::D,
+	KeyCode::E,
+	KeyCode::F,
+	KeyCode::G,
+	KeyCode::H,
+	KeyCode::I,
+	KeyCode::J,
+	KeyCode::K,
+	KeyCode::L,
+	KeyCode::M,
+	KeyCode::N,
+	KeyCode::O,
+	KeyCode::P,
+	KeyCode::Q,
+	KeyCode::R,
+	KeyCode::S,
+	KeyCode::T,
+	KeyCode::U,
+	KeyCode::V,
+	KeyCode::W,
+	KeyCode::X,
+	KeyCode::Y,
+	KeyCode::Z,
+];
+
+const DIGITS: [KeyCode; 10] = [
+	KeyCode::Digit0,
+	KeyCode::Digit1,
+	KeyCode::Digit2,
+	KeyCode::Digit3,
+	KeyCode::Digit4,
+	KeyCode::Digit5,
+	KeyCode::Digit6,
+	KeyCode::Digit7,
+	KeyCode::Digit8,
+	KeyCode::Digit9,
+];
+
+impl KeyCode {
+	/// The logical key of a key event, told apart from the main keyboard by `location` for keys
+	/// on the numeric keypad.
+	pub(crate) fn logical(key: &Key, location: KeyLocation) -> Self {
+		let code = KeyCode::from(key);
+		if location != KeyLocation::Numpad {
+			return code;
+		}
+
+		match code {
+			KeyCode::Digit0 => KeyCode::Numpad0,
+			KeyCode::Digit1 => KeyCode::Numpad1,
+			KeyCode::Digit2 => KeyCode::Numpad2,
+			KeyCode::Digit3 => KeyCode::Numpad3,
+			KeyCode::Digit4 => KeyCode::Numpad4,
+			KeyCode::Digit5 => KeyCode::Numpad5,
+			KeyCode::Digit6 => KeyCode::Numpad6,
+			KeyCode::Digit7 => KeyCode::Numpad7,
+			KeyCode::Digit8 => KeyCode::Numpad8,
+			KeyCode::Digit9 => KeyCode::Numpad9,
+			KeyCode::Character('+') => KeyCode::NumpadAdd,
+			KeyCode::Character('-') => KeyCode::NumpadSubtract,
+			KeyCode::Character('*') => KeyCode::NumpadMultiply,
+			KeyCode::Character('/') => KeyCode::NumpadDivide,
+			// the decimal key types a comma with some layouts
+			KeyCode::Character('.' | ',') => KeyCode::NumpadDecimal,
+			KeyCode::Character('=') => KeyCode::NumpadEqual,
+			KeyCode::Enter => KeyCode::NumpadEnter,
+			code => code,
+		}
+	}
+}
+
+/// Logs a key without a [`KeyCode`] at trace level, once per key rather than on every press, to
+/// help find gaps in the mapping.
+pub(crate) fn trace_unmapped(key: &dyn fmt::Debug) {
+	static REPORTED: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(Mutex::default);
+
+	let key = format!("{key:?}");
+	let mut reported = REPORTED.lock().unwrap_or_else(PoisonError::into_inner);
+	if !reported.contains(&key) {
+		core_trace!("No KeyCode for {key}");
+		reported.insert(key);
+	}
+}
+
+impl From<&Key> for KeyCode {
+	fn from(value: &Key) -> Self {
+		match value {
+			Key::Character(text) => {
+				let mut characters = text.chars();
+				match (characters.next(), characters.next()) {
+					(Some(character), None) => KeyCode::from(character),
+					_ => KeyCode::Unknown,
+				}
+			},
+			Key::Named(named) => KeyCode::from(*named),
+			Key::Dead(_) | Key::Unidentified(_) => KeyCode::Unknown,
+		}
+	}
+}
+
+impl From<char> for KeyCode {
+	fn from(value: char) -> Self {
+		if value.is_ascii_alphabetic() {
+			LETTERS[(value.to_ascii_uppercase() as u8 - b'A') as usize]
+		}
+		else if value.is_ascii_digit() {
+			DIGITS[(value as u8 - b'0') as usize]
+		}
+		else if value == ' ' {
+			KeyCode::Space
+		}
+		else {
+			KeyCode::Character(value)
+		}
+	}
+}
+
+impl From<NamedKey> for KeyCode {
+	fn from(value: NamedKey) -> Self {
+		match value {
+			NamedKey::F1 => KeyCode::F1,
+			NamedKey::F2 => KeyCode::F2,
+			NamedKey::F3 => KeyCode::F3,
+			NamedKey::F4 => KeyCode::F4,
+			NamedKey::F5 => KeyCode::F5,
+			NamedKey::F6 => KeyCode::F6,
+			NamedKey::F7 => KeyCode::F7,
+			NamedKey::F8 => KeyCode::F8,
+			NamedKey::F9 => KeyCode::F9,
+			NamedKey::F10 => KeyCode::F10,
+			NamedKey::F11 => KeyCode::F11,
+			NamedKey::F12 => KeyCode::F12,
+			NamedKey::Space => KeyCode::Space,
+			NamedKey::Enter => KeyCode::Enter,
+			NamedKey::Escape => KeyCode::Escape,
+			NamedKey::Tab => KeyCode::Tab,
+			NamedKey::Backspace => KeyCode::Backspace,
+			NamedKey::Delete => KeyCode::Delete,
+			NamedKey::Insert => KeyCode::Insert,
+			NamedKey::Home => KeyCode::Home,
+			NamedKey::End => KeyCode::End,
+			NamedKey::PageUp => KeyCode::PageUp,
+			NamedKey::PageDown => KeyCode::PageDown,
+			NamedKey::ArrowUp => KeyCode::ArrowUp,
+			NamedKey::ArrowDown => KeyCode::ArrowDown,
+			NamedKey::ArrowLeft => KeyCode::ArrowLeft,
+			NamedKey::ArrowRight => KeyCode::ArrowRight,
+			NamedKey::Shift => KeyCode::Shift,
+			NamedKey::Control => KeyCode::Control,
+			NamedKey::Alt => KeyCode::Alt,
+			NamedKey::Super => KeyCode::Super,
+			NamedKey::CapsLock => KeyCode::CapsLock,
+			NamedKey::NumLock => KeyCode::NumLock,
+			NamedKey::ScrollLock => KeyCode::ScrollLock,
+			NamedKey::PrintScreen => KeyCode::PrintScreen,
+			NamedKey::Pause => KeyCode::Pause,
+			NamedKey::ContextMenu => KeyCode::ContextMenu,
+			NamedKey::AudioVolumeUp => KeyCode::AudioVolumeUp,
+			NamedKey::AudioVolumeDown => KeyCode::AudioVolumeDown,
+			NamedKey::AudioVolumeMute => KeyCode::AudioVolumeMute,
+			NamedKey::MediaPlayPause => KeyCode::MediaPlayPause,
+			NamedKey::MediaStop => KeyCode::MediaStop,
+			NamedKey::MediaTrackNext => KeyCode::MediaTrackNext,
+			NamedKey::MediaTrackPrevious => KeyCode::MediaTrackPrevious,
+			_ => KeyCode::Unknown,
+		}
+	}
+}
+
+impl From<PhysicalKey> for KeyCode {
+	#[allow(clippy::too_many_lines)] // a flat lookup table
+	fn from(value: PhysicalKey) -> Self {
+		use keyboard::KeyCode as Code;
+
+		let PhysicalKey::Code(code) = value
+		else {
+			return KeyCode::Unknown;
+		};
+		match code {
+			Code::KeyA => KeyCode::A,
+			Code::KeyB => KeyCode::B,
+			Code::KeyC => KeyCode::C,
+			Code::KeyD => KeyCode::D,
+			Code::KeyE => KeyCode::E,
+			Code::KeyF => KeyCode::F,
+			Code::KeyG => KeyCode::G,
+			Code::KeyH => KeyCode::H,
+			Code::KeyI => KeyCode::I,
+			Code::KeyJ => KeyCode::J,
+			Code::KeyK => KeyCode::K,
+			Code::KeyL => KeyCode::L,
+			Code::KeyM => KeyCode::M,
+			Code::KeyN => KeyCode::N,
+			Code::KeyO => KeyCode::O,
+			Code::KeyP => KeyCode::P,
+			Code::KeyQ => KeyCode::Q,
+			Code::KeyR => KeyCode::R,
+			Code::KeyS => KeyCode::S,
+			Code::KeyT => KeyCode::T,
+			Code::KeyU => KeyCode::U,
+			Code::KeyV => KeyCode::V,
+			Code::KeyW => KeyCode::W,
+			Code::KeyX => KeyCode::X,
+			Code::KeyY => KeyCode::Y,
+			Code::KeyZ => KeyCode::Z,
+			Code::Digit0 => KeyCode::Digit0,
+			Code::Digit1 => KeyCode::Digit1,
+			Code::Digit2 => KeyCode::Digit2,
+			Code::Digit3 => KeyCode::Digit3,
+			Code::Digit4 => KeyCode::Digit4,
+			Code::Digit5 => KeyCode::Digit5,
+			Code::Digit6 => KeyCode::Digit6,
+			Code::Digit7 => KeyCode::Digit7,
+			Code::Digit8 => KeyCode::Digit8,
+			Code::Digit9 => KeyCode::Digit9,
+			Code::F1 => KeyCode::F1,
+			Code::F2 => KeyCode::F2,
+			Code::F3 => KeyCode::F3,
+			Code::F4 => KeyCode::F4,
+			Code::F5 => KeyCode::F5,
+			Code::F6 => KeyCode::F6,
+			Code::F7 => KeyCode::F7,
+			Code::F8 => KeyCode::F8,
+			Code::F9 => KeyCode::F9,
+			Code::F10 => KeyCode::F10,
+			Code::F11 => KeyCode::F11,
+			Code::F12 => KeyCode::F12,
+			Code::Space => KeyCode::Space,
+			Code::Enter => KeyCode::Enter,
+			Code::Escape => KeyCode::Escape,
+			Code::Tab => KeyCode::Tab,
+			Code::Backspace => KeyCode::Backspace,
+			Code::Delete => KeyCode::Delete,
+			Code::Insert => KeyCode::Insert,
+			Code::Home => KeyCode::Home,
+			Code::End => KeyCode::End,
+			Code::PageUp => KeyCode::PageUp,
+			Code::PageDown => KeyCode::PageDown,
+			Code::ArrowUp => KeyCode::ArrowUp,
+			Code::ArrowDown => KeyCode::ArrowDown,
+			Code::ArrowLeft => KeyCode::ArrowLeft,
+			Code::ArrowRight => KeyCode::ArrowRight,
+			Code::ShiftLeft | Code::ShiftRight => KeyCode::Shift,
+			Code::ControlLeft | Code::ControlRight => KeyCode::Control,
+			Code::AltLeft | Code::AltRight => KeyCode::Alt,
+			Code::SuperLeft | Code::SuperRight => KeyCode::Super,
+			Code::CapsLock => KeyCode::CapsLock,
+			Code::NumLock => KeyCode::NumLock,
+			Code::ScrollLock => KeyCode::ScrollLock,
+			Code::PrintScreen => KeyCode::PrintScreen,
+			Code::Pause => KeyCode::Pause,
+			Code::ContextMenu => KeyCode::ContextMenu,
+			Code::Backquote => KeyCode::Character('`'),
+			Code::Minus => KeyCode::Character('-'),
+			Code::Equal => KeyCode::Character('='),
+			Code::BracketLeft => KeyCode::Character('['),
+			Code::BracketRight => KeyCode::Character(']'),
+			Code::Backslash => KeyCode::Character('\\'),
+			Code::Semicolon => KeyCode::Character(';'),
+			Code::Quote => KeyCode::Character('\''),
+			Code::Comma => KeyCode::Character(','),
+			Code::Period => KeyCode::Character('.'),
+			Code::Slash => KeyCode::Character('/'),
+			Code::Numpad0 => KeyCode::Numpad0,
+			Code::Numpad1 => KeyCode::Numpad1,
+			Code::Numpad2 => KeyCode::Numpad2,
+			Code::Numpad3 => KeyCode::Numpad3,
+			Code::Numpad4 => KeyCode::Numpad4,
+			Code::Numpad5 => KeyCode::Numpad5,
+			Code::Numpad6 => KeyCode::Numpad6,
+			Code::Numpad7 => KeyCode::Numpad7,
+			Code::Numpad8 => KeyCode::Numpad8,
+			Code::Numpad9 => KeyCode::Numpad9,
+			Code::NumpadAdd => KeyCode::NumpadAdd,
+			Code::NumpadSubtract => KeyCode::NumpadSubtract,
+			Code::NumpadMultiply => KeyCode::NumpadMultiply,
+			Code::NumpadDivide => KeyCode::NumpadDivide,
+			Code::NumpadDecimal => KeyCode::NumpadDecimal,
+			Code::NumpadEqual => KeyCode::NumpadEqual,
+			Code::NumpadEnter => KeyCode::NumpadEnter,
+			Code::AudioVolumeUp => KeyCode::AudioVolumeUp,
+			Code::AudioVolumeDown => KeyCode::AudioVolumeDown,
+			Code::AudioVolumeMute => KeyCode::AudioVolumeMute,
+			Code::MediaPlayPause => KeyCode::MediaPlayPause,
+			Code::MediaStop => KeyCode::MediaStop,
+			Code::MediaTrackNext => KeyCode::MediaTrackNext,
+			Code::MediaTrackPrevious => KeyCode::MediaTrackPrevious,
+			_ => KeyCode::Unknown,
+		}
+	}
+}
+
+/// Displays printable keys as their character and other keys by name, e.g. `A`, `Tab` or `ß`.
+impl Display for KeyCode {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			KeyCode::Character(character) => write!(f, "{character}"),
+			key => write!(f, "{key:?}"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use winit::keyboard::{
+		Key, KeyCode as Code, KeyLocation, NamedKey, NativeKey, NativeKeyCode, PhysicalKey,
+	};
+
+	use super::KeyCode;
+
+	fn character(text: &str, location: KeyLocation) -> KeyCode {
+		KeyCode::logical(&Key::Character(text.into()), location)
+	}
+
+	#[test]
+	fn numpad_keys_map_to_numpad_codes() {
+		assert_eq!(character("0", KeyLocation::Numpad), KeyCode::Numpad0);
+		assert_eq!(character("7", KeyLocation::Numpad), KeyCode::Numpad7);
+		assert_eq!(character("+", KeyLocation::Numpad), KeyCode::NumpadAdd);
+		assert_eq!(character("-", KeyLocation::Numpad), KeyCode::NumpadSubtract);
+		assert_eq!(character("*", KeyLocation::Numpad), KeyCode::NumpadMultiply);
+		assert_eq!(character("/", KeyLocation::Numpad), KeyCode::NumpadDivide);
+		assert_eq!(character(".", KeyLocation::Numpad), KeyCode::NumpadDecimal);
+		assert_eq!(character(",", KeyLocation::Numpad), KeyCode::NumpadDecimal);
+		assert_eq!(character("=", KeyLocation::Numpad), KeyCode::NumpadEqual);
+		let enter = Key::Named(NamedKey::Enter);
+		assert_eq!(KeyCode::logical(&enter, KeyLocation::Numpad), KeyCode::NumpadEnter);
+
+		// the same keys elsewhere keep their main keyboard codes
+		assert_eq!(character("7", KeyLocation::Standard), KeyCode::Digit7);
+		assert_eq!(character("+", KeyLocation::Standard), KeyCode::Character('+'));
+		assert_eq!(KeyCode::logical(&enter, KeyLocation::Standard), KeyCode::Enter);
+		// navigation keys on the numpad with Num Lock off
+		let home = Key::Named(NamedKey::Home);
+		assert_eq!(KeyCode::logical(&home, KeyLocation::Numpad), KeyCode::Home);
+	}
+
+	#[test]
+	fn media_keys_map_to_media_codes() {
+		let rows = [
+			(NamedKey::AudioVolumeUp, Code::AudioVolumeUp, KeyCode::AudioVolumeUp),
+			(NamedKey::AudioVolumeDown, Code::AudioVolumeDown, KeyCode::AudioVolumeDown),
+			(NamedKey::AudioVolumeMute, Code::AudioVolumeMute, KeyCode::AudioVolumeMute),
+			(NamedKey::MediaPlayPause, Code::MediaPlayPause, KeyCode::MediaPlayPause),
+			(NamedKey::MediaStop, Code::MediaStop, KeyCode::MediaStop),
+			(NamedKey::MediaTrackNext, Code::MediaTrackNext, KeyCode::MediaTrackNext),
+			(NamedKey::MediaTrackPrevious, Code::MediaTrackPrevious, KeyCode::MediaTrackPrevious),
+		];
+		for (named, code, expected) in rows {
+			let logical = KeyCode::logical(&Key::Named(named), KeyLocation::Standard);
+			assert_eq!(logical, expected, "{named:?}");
+			assert_eq!(KeyCode::from(PhysicalKey::Code(code)), expected, "{code:?}");
+		}
+	}
+
+	#[test]
+	fn letters_fold_case() {
+		assert_eq!(KeyCode::from('a'), KeyCode::A);
+		assert_eq!(KeyCode::from('A'), KeyCode::A);
+		assert_eq!(KeyCode::from('z'), KeyCode::Z);
+		assert_eq!(character("Q", KeyLocation::Standard), KeyCode::Q);
+		// only ASCII letters have variants, others keep their case
+		assert_eq!(KeyCode::from('é'), KeyCode::Character('é'));
+		assert_eq!(KeyCode::from('É'), KeyCode::Character('É'));
+		assert_eq!(KeyCode::from('5'), KeyCode::Digit5);
+		assert_eq!(KeyCode::from(' '), KeyCode::Space);
+	}
+
+	#[test]
+	fn unmapped_keys_are_unknown() {
+		assert_eq!(character("ab", KeyLocation::Standard), KeyCode::Unknown);
+		assert_eq!(character("", KeyLocation::Standard), KeyCode::Unknown);
+		let dead = Key::Dead(Some('^'));
+		assert_eq!(KeyCode::logical(&dead, KeyLocation::Standard), KeyCode::Unknown);
+		let unidentified = Key::Unidentified(NativeKey::Unidentified);
+		assert_eq!(KeyCode::logical(&unidentified, KeyLocation::Numpad), KeyCode::Unknown);
+		assert_eq!(KeyCode::from(NamedKey::Hyper), KeyCode::Unknown);
+
+		let physical = PhysicalKey::Unidentified(NativeKeyCode::Unidentified);
+		assert_eq!(KeyCode::from(physical), KeyCode::Unknown);
+	}
+}
@@ -1,23 +1,248 @@
-use std::iter::Rev;
-use std::ops::DerefMut;
-use std::slice;
+#[cfg(feature = "profile")]
+use std::time::Instant;
+use std::{
+	any::Any,
+	collections::{HashMap, HashSet},
+	fmt,
+	iter::Rev,
+	ops::Range,
+	slice,
+	time::Duration,
+};
+
+use event::{Event, EventCategory};
+use wgpu::RenderPass;
 use winit::event_loop::ActiveEventLoop;
-use event::Event;
-use crate::event;
 
-#[derive(Copy, Clone, PartialOrd, PartialEq, Ord, Eq)]
+use crate::{context::LayerContext, core_warn, event};
+
+/// Identifies a layer in its [`LayerStack`]. Ids are never reused within a stack.
+#[derive(Copy, Clone, Debug, PartialOrd, PartialEq, Ord, Eq, Hash)]
 pub struct LayerId(usize);
 
-pub trait Layer {
+impl LayerId {
+	/// The id as a number, e.g. for logs or as a key outside the engine.
+	#[must_use]
+	pub fn id(self) -> usize {
+		self.0
+	}
+}
+
+/// Displays the id as `#` followed by the number, e.g. `#3`.
+impl fmt::Display for LayerId {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "#{}", self.0)
+	}
+}
+
+/// Access to a layer as [`Any`], for downcasting a `dyn Layer` back to its concrete type with
+/// [`LayerStack::get_as`]. Implemented for every `'static` type, so layers never implement it by
+/// hand.
+pub trait AsAny: Any {
+	fn as_any(&self) -> &dyn Any;
+	fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+	fn as_any(&self) -> &dyn Any {
+		self
+	}
+
+	fn as_any_mut(&mut self) -> &mut dyn Any {
+		self
+	}
+}
+
+/// What a layer did with an event in [`Layer::on_event`], deciding whether it travels further
+/// down the stack.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum EventResult {
+	/// The layer consumed the event; the layers below do not see it.
+	Handled,
+	/// The layer ignored the event.
+	#[default]
+	Pass,
+	/// The layer reacted to the event but leaves it to the layers below as well, e.g. a HUD
+	/// watching input without stealing it.
+	Observe,
+}
+
+impl EventResult {
+	/// Whether the event stops at this layer.
+	#[must_use]
+	pub fn is_handled(self) -> bool {
+		self == EventResult::Handled
+	}
+}
+
+/// `true` is [`EventResult::Handled`] and `false` is [`EventResult::Pass`].
+impl From<bool> for EventResult {
+	fn from(value: bool) -> Self {
+		if value {
+			EventResult::Handled
+		}
+		else {
+			EventResult::Pass
+		}
+	}
+}
+
+pub trait Layer: AsAny {
 	fn name(&self) -> &str;
-	fn on_event(&mut self, _event_loop: &ActiveEventLoop, _event: &Event) -> bool { false }
+	/// Called once per frame, bottom of the stack first. With
+	/// [`crate::WindowSpec::fixed_timestep`] set, called once per step instead, which can be
+	/// zero or several times in a frame.
+	fn on_update(&mut self, _context: &mut LayerContext) {}
+	/// Called after the updates of a frame once [`LayerStack::tick_interval`] has passed, one
+	/// second by default, for slow periodic work like showing the FPS in the window title. At most
+	/// one tick runs per frame, so ticks are late when frames are slower than the interval, and
+	/// catch up on the following frames. Not meant for simulation, which should scale by the
+	/// delta time instead.
+	fn on_tick(&mut self, _context: &mut LayerContext) {}
+	/// Called once per frame after all updates, bottom of the stack first, so overlays draw over
+	/// the layers below them.
+	fn on_render(&mut self, _context: &LayerContext, _render_pass: &mut RenderPass) {}
+	/// Called before the next update after the window surface was recreated on resume, e.g. on
+	/// Android. The device is kept across a suspend, so only resources tied to the surface need to
+	/// be recreated.
+	fn on_surface_recreated(&mut self, _context: &LayerContext) {}
+	/// Called top of the stack first, the reverse of the render order. Returning
+	/// [`EventResult::Handled`] stops the event from reaching the layers below; `Pass` and
+	/// `Observe` let it continue.
+	fn on_event(&mut self, _event_loop: &ActiveEventLoop, _event: &Event) -> EventResult {
+		EventResult::Pass
+	}
+	/// The categories of events passed to `on_event`. Events outside the mask skip this layer and
+	/// continue down the stack.
+	fn event_mask(&self) -> EventCategory {
+		EventCategory::all()
+	}
+}
+
+/// A layer that only handles events, built from a closure instead of a dedicated type, e.g.
+/// `layer_stack.push_layer(FnLayer::new("input", |_event_loop, event| EventResult::Pass))`.
+pub struct FnLayer<F> {
+	name: String,
+	on_event: F,
+}
+
+impl<F> FnLayer<F>
+where F: FnMut(&ActiveEventLoop, &Event) -> EventResult + 'static
+{
+	pub fn new(name: impl Into<String>, on_event: F) -> Self {
+		FnLayer { name: name.into(), on_event }
+	}
+}
+
+impl<F> Layer for FnLayer<F>
+where F: FnMut(&ActiveEventLoop, &Event) -> EventResult + 'static
+{
+	fn name(&self) -> &str {
+		&self.name
+	}
+
+	fn on_event(&mut self, event_loop: &ActiveEventLoop, event: &Event) -> EventResult {
+		(self.on_event)(event_loop, event)
+	}
 }
 
 pub struct LayerStack {
 	ids: Vec<LayerId>,
 	data: Vec<Box<dyn Layer>>,
-	layer_insert: usize, // one past the last overlay
+	layer_insert: usize, // one past the last layer, i.e. the index of the first overlay
 	next_layer_id: usize,
+	disabled: HashSet<LayerId>,
+	timings: LayerTimings,
+	tick_interval: Duration,
+	/// Time since the last tick.
+	tick_elapsed: Duration,
+}
+
+/// Time spent in the callbacks of each layer, only measured with the `profile` feature.
+#[derive(Default)]
+struct LayerTimings {
+	#[cfg(feature = "profile")]
+	durations: HashMap<LayerId, Duration>,
+}
+
+impl LayerTimings {
+	#[cfg(feature = "profile")]
+	fn measure<R>(&mut self, layer_id: LayerId, callback: impl FnOnce() -> R) -> R {
+		let start = Instant::now();
+		let result = callback();
+		*self.durations.entry(layer_id).or_default() += start.elapsed();
+		result
+	}
+
+	#[cfg(not(feature = "profile"))]
+	#[allow(clippy::unused_self)]
+	fn measure<R>(&mut self, _layer_id: LayerId, callback: impl FnOnce() -> R) -> R {
+		callback()
+	}
+
+	#[cfg(feature = "profile")]
+	fn take(&mut self, layer_id: LayerId) -> Option<Duration> {
+		self.durations.remove(&layer_id)
+	}
+
+	#[cfg(not(feature = "profile"))]
+	#[allow(clippy::unused_self)]
+	fn take(&mut self, _layer_id: LayerId) -> Option<Duration> {
+		None
+	}
+}
+
+/// The composition of a [`LayerStack`], without the state of the layers themselves, for saving
+/// and restoring e.g. an editor's panel layout.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StackSnapshot {
+	/// Bottom of the stack first.
+	pub layers: Vec<LayerSnapshot>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayerSnapshot {
+	pub name: String,
+	pub enabled: bool,
+	pub is_overlay: bool,
+}
+
+/// Constructors for layers by name, used by [`LayerStack::restore`] to rebuild a stack from a
+/// [`StackSnapshot`].
+#[derive(Default)]
+pub struct LayerRegistry {
+	constructors: HashMap<String, Box<dyn Fn() -> Box<dyn Layer>>>,
+}
+
+impl LayerRegistry {
+	#[must_use]
+	pub fn new() -> Self {
+		LayerRegistry::default()
+	}
+
+	/// Registers a constructor for layers with the given name, which should match what the
+	/// constructed layer returns from [`Layer::name`].
+	pub fn register<L: Layer + 'static>(
+		&mut self,
+		name: impl Into<String>,
+		constructor: impl Fn() -> L + 'static,
+	) {
+		self.constructors.insert(name.into(), Box::new(move || Box::new(constructor())));
+	}
+}
+
+pub struct Iter<'data> {
+	inner: Rev<slice::Iter<'data, Box<dyn Layer>>>,
+}
+
+impl<'data> Iterator for Iter<'data> {
+	type Item = &'data dyn Layer;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.inner.next().map(|it| &**it as &dyn Layer)
+	}
 }
 
 pub struct IterMut<'data> {
@@ -28,20 +253,46 @@ impl<'data> Iterator for IterMut<'data> {
 	type Item = &'data mut dyn Layer;
 
 	fn next(&mut self) -> Option<Self::Item> {
-		self.inner.next().map(|it| { it.deref_mut() })
+		self.inner.next().map(|it| &mut **it as &mut dyn Layer)
+	}
+}
+
+pub struct IterMutWithIds<'data> {
+	ids: Rev<slice::Iter<'data, LayerId>>,
+	inner: Rev<slice::IterMut<'data, Box<dyn Layer>>>,
+}
+
+impl<'data> Iterator for IterMutWithIds<'data> {
+	type Item = (LayerId, &'data mut dyn Layer);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		Some((*self.ids.next()?, &mut **self.inner.next()? as &mut dyn Layer))
 	}
 }
 
 impl LayerStack {
 	pub(crate) fn new() -> LayerStack {
-		LayerStack { ids: Vec::new(), data: Vec::new(), layer_insert: 0, next_layer_id: 1 }
+		LayerStack {
+			ids: Vec::new(),
+			data: Vec::new(),
+			layer_insert: 0,
+			next_layer_id: 1,
+			disabled: HashSet::new(),
+			timings: LayerTimings::default(),
+			tick_interval: Duration::from_secs(1),
+			tick_elapsed: Duration::ZERO,
+		}
 	}
 
 	pub fn push_layer(&mut self, layer: impl Layer + 'static) -> LayerId {
+		self.push_boxed_layer(Box::new(layer))
+	}
+
+	fn push_boxed_layer(&mut self, layer: Box<dyn Layer>) -> LayerId {
 		let layer_id = LayerId(self.next_layer_id);
 
 		self.ids.insert(self.layer_insert, layer_id);
-		self.data.insert(self.layer_insert, Box::new(layer));
+		self.data.insert(self.layer_insert, layer);
 
 		self.next_layer_id += 1;
 		self.layer_insert += 1;
@@ -49,11 +300,76 @@ impl LayerStack {
 		layer_id
 	}
 
+	/// Inserts a layer directly below the layer `anchor`, so it updates and renders just before
+	/// it and sees events just after it. Returns `None`, dropping `layer`, if `anchor` is not a
+	/// layer of this stack, e.g. because it is an overlay.
+	pub fn insert_layer_before(
+		&mut self,
+		anchor: LayerId,
+		layer: impl Layer + 'static,
+	) -> Option<LayerId> {
+		let index = self.ids[..self.layer_insert].iter().position(|it| *it == anchor)?;
+		self.layer_insert += 1;
+		Some(self.insert_boxed(index, Box::new(layer)))
+	}
+
+	/// Inserts a layer directly above the layer `anchor`, see
+	/// [`LayerStack::insert_layer_before`].
+	pub fn insert_layer_after(
+		&mut self,
+		anchor: LayerId,
+		layer: impl Layer + 'static,
+	) -> Option<LayerId> {
+		let index = self.ids[..self.layer_insert].iter().position(|it| *it == anchor)?;
+		self.layer_insert += 1;
+		Some(self.insert_boxed(index + 1, Box::new(layer)))
+	}
+
+	/// Inserts an overlay directly below the overlay `anchor`. Returns `None`, dropping
+	/// `overlay`, if `anchor` is not an overlay of this stack.
+	pub fn insert_overlay_before(
+		&mut self,
+		anchor: LayerId,
+		overlay: impl Layer + 'static,
+	) -> Option<LayerId> {
+		let index =
+			self.ids[self.layer_insert..].iter().position(|it| *it == anchor)? + self.layer_insert;
+		Some(self.insert_boxed(index, Box::new(overlay)))
+	}
+
+	/// Inserts an overlay directly above the overlay `anchor`, see
+	/// [`LayerStack::insert_overlay_before`].
+	pub fn insert_overlay_after(
+		&mut self,
+		anchor: LayerId,
+		overlay: impl Layer + 'static,
+	) -> Option<LayerId> {
+		let index =
+			self.ids[self.layer_insert..].iter().position(|it| *it == anchor)? + self.layer_insert;
+		Some(self.insert_boxed(index + 1, Box::new(overlay)))
+	}
+
+	/// Inserts at `index` without moving the partition, which the caller adjusts.
+	fn insert_boxed(&mut self, index: usize, layer: Box<dyn Layer>) -> LayerId {
+		let layer_id = LayerId(self.next_layer_id);
+
+		self.ids.insert(index, layer_id);
+		self.data.insert(index, layer);
+
+		self.next_layer_id += 1;
+
+		layer_id
+	}
+
 	pub fn push_overlay(&mut self, overlay: impl Layer + 'static) -> LayerId {
+		self.push_boxed_overlay(Box::new(overlay))
+	}
+
+	fn push_boxed_overlay(&mut self, overlay: Box<dyn Layer>) -> LayerId {
 		let layer_id = LayerId(self.next_layer_id);
 
 		self.ids.push(layer_id);
-		self.data.push(Box::new(overlay));
+		self.data.push(overlay);
 
 		self.next_layer_id += 1;
 
@@ -61,29 +377,643 @@ impl LayerStack {
 	}
 
 	pub fn pop_layer(&mut self, layer_id: LayerId) -> Option<Box<dyn Layer>> {
-		self.ids.iter().position(|it| *it == layer_id)
-			.map(|index| {
-				self.layer_insert -= 1;
-				self.data.remove(index)
-			})
+		let index = self.ids[..self.layer_insert].iter().position(|it| *it == layer_id)?;
+		self.layer_insert -= 1;
+		self.ids.remove(index);
+		self.disabled.remove(&layer_id);
+		Some(self.data.remove(index))
 	}
 
 	pub fn pop_overlay(&mut self, layer_id: LayerId) -> Option<Box<dyn Layer>> {
+		let index = self.ids[self.layer_insert..].iter().position(|it| *it == layer_id)?
+			+ self.layer_insert;
+		self.ids.remove(index);
+		self.disabled.remove(&layer_id);
+		Some(self.data.remove(index))
+	}
+
+	/// Disabled layers stay in the stack but are skipped for updates, rendering and events.
+	/// Returns `false` if the id is not in the stack.
+	pub fn set_enabled(&mut self, layer_id: LayerId, enabled: bool) -> bool {
+		if self.position(layer_id).is_none() {
+			return false;
+		}
+		if enabled {
+			self.disabled.remove(&layer_id);
+		}
+		else {
+			self.disabled.insert(layer_id);
+		}
+		true
+	}
+
+	/// Whether the layer is in the stack and enabled.
+	#[must_use]
+	pub fn is_enabled(&self, layer_id: LayerId) -> bool {
+		self.position(layer_id).is_some() && !self.disabled.contains(&layer_id)
+	}
+
+	#[must_use]
+	pub fn snapshot(&self) -> StackSnapshot {
+		let layers = self
+			.ids
+			.iter()
+			.zip(&self.data)
+			.enumerate()
+			.map(|(index, (layer_id, layer))| LayerSnapshot {
+				name: layer.name().to_string(),
+				enabled: !self.disabled.contains(layer_id),
+				is_overlay: index >= self.layer_insert,
+			})
+			.collect();
+		StackSnapshot { layers }
+	}
+
+	/// Replaces the contents of the stack with new layers built from `snapshot`. Layers whose
+	/// name has no constructor in `registry` are skipped with a warning.
+	pub fn restore(&mut self, snapshot: &StackSnapshot, registry: &LayerRegistry) {
+		self.ids.clear();
+		self.data.clear();
+		self.layer_insert = 0;
+		self.disabled.clear();
+
+		for LayerSnapshot { name, enabled, is_overlay } in &snapshot.layers {
+			let Some(constructor) = registry.constructors.get(name)
+			else {
+				core_warn!("No constructor registered for layer {name:?}");
+				continue;
+			};
+
+			let layer_id = if *is_overlay {
+				self.push_boxed_overlay(constructor())
+			}
+			else {
+				self.push_boxed_layer(constructor())
+			};
+			self.set_enabled(layer_id, *enabled);
+		}
+	}
+
+	/// Moves a layer to the top of its region, so it draws last and receives events first among
+	/// the layers (or overlays). Returns `false` if the id is not in the stack.
+	pub fn move_to_front(&mut self, layer_id: LayerId) -> bool {
+		let Some(index) = self.position(layer_id)
+		else {
+			return false;
+		};
+		let region = self.region(index);
+		self.move_index(index, region.end - 1);
+		true
+	}
+
+	/// Moves a layer to the bottom of its region, so it draws first and receives events last among
+	/// the layers (or overlays). Returns `false` if the id is not in the stack.
+	pub fn move_to_back(&mut self, layer_id: LayerId) -> bool {
+		let Some(index) = self.position(layer_id)
+		else {
+			return false;
+		};
+		let region = self.region(index);
+		self.move_index(index, region.start);
+		true
+	}
+
+	/// Swaps the positions of two layers. Layers cannot be swapped with overlays, so this returns
+	/// `false` if the ids are in different regions or either is not in the stack.
+	pub fn swap(&mut self, first: LayerId, second: LayerId) -> bool {
+		let (Some(first), Some(second)) = (self.position(first), self.position(second))
+		else {
+			return false;
+		};
+		if self.region(first) != self.region(second) {
+			return false;
+		}
+		self.ids.swap(first, second);
+		self.data.swap(first, second);
+		true
+	}
+
+	/// The interval between calls to [`Layer::on_tick`].
+	#[must_use]
+	pub fn tick_interval(&self) -> Duration {
+		self.tick_interval
+	}
+
+	/// # Panics
+	/// Panics if `interval` is zero.
+	pub fn set_tick_interval(&mut self, interval: Duration) {
+		assert!(!interval.is_zero(), "The tick interval must not be zero");
+		self.tick_interval = interval;
+		self.tick_elapsed = self.tick_elapsed.min(interval);
+	}
+
+	#[must_use]
+	pub fn get(&self, layer_id: LayerId) -> Option<&dyn Layer> {
+		self.position(layer_id).map(|index| self.data[index].as_ref())
+	}
+
+	#[must_use]
+	pub fn get_mut(&mut self, layer_id: LayerId) -> Option<&mut dyn Layer> {
+		self.position(layer_id).map(|index| self.data[index].as_mut())
+	}
+
+	/// The layer with the given id as its concrete type, or `None` if the id is not in the stack or
+	/// the layer is of another type.
+	#[must_use]
+	pub fn get_as<T: Layer>(&self, layer_id: LayerId) -> Option<&T> {
+		let layer = self.get(layer_id)?;
+		// deref explicitly, so the call does not resolve to the blanket impl for the reference
+		(*layer).as_any().downcast_ref()
+	}
+
+	#[must_use]
+	pub fn get_as_mut<T: Layer>(&mut self, layer_id: LayerId) -> Option<&mut T> {
+		let layer = self.get_mut(layer_id)?;
+		(*layer).as_any_mut().downcast_mut()
+	}
+
+	fn position(&self, layer_id: LayerId) -> Option<usize> {
 		self.ids.iter().position(|it| *it == layer_id)
-			.map(|index| self.data.remove(index))
+	}
+
+	/// The index range of the layers or overlays, whichever `index` falls into.
+	fn region(&self, index: usize) -> Range<usize> {
+		if index < self.layer_insert {
+			0..self.layer_insert
+		}
+		else {
+			self.layer_insert..self.ids.len()
+		}
+	}
+
+	fn move_index(&mut self, from: usize, to: usize) {
+		let layer_id = self.ids.remove(from);
+		let layer = self.data.remove(from);
+		self.ids.insert(to, layer_id);
+		self.data.insert(to, layer);
+	}
+
+	/// The number of layers plus overlays.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.data.len()
+	}
+
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.data.is_empty()
+	}
+
+	#[must_use]
+	pub fn layer_count(&self) -> usize {
+		self.layer_insert
+	}
+
+	#[must_use]
+	pub fn overlay_count(&self) -> usize {
+		self.data.len() - self.layer_insert
+	}
+
+	/// Iterates top-of-stack first, in the same order as [`LayerStack::iter_mut`].
+	#[must_use]
+	pub fn iter(&self) -> Iter<'_> {
+		Iter { inner: self.data.iter().rev() }
 	}
 
 	#[must_use]
 	pub fn iter_mut(&mut self) -> IterMut<'_> {
 		IterMut { inner: self.data.iter_mut().rev() }
 	}
+
+	/// Like [`LayerStack::iter_mut`], along with the id of each layer.
+	#[must_use]
+	pub fn iter_mut_with_ids(&mut self) -> IterMutWithIds<'_> {
+		IterMutWithIds { ids: self.ids.iter().rev(), inner: self.data.iter_mut().rev() }
+	}
+
+	/// Tells every layer, enabled or not, that the window surface was recreated.
+	pub(crate) fn surface_recreated(&mut self, context: &LayerContext) {
+		for layer in &mut self.data {
+			layer.on_surface_recreated(context);
+		}
+	}
+
+	/// Updates the enabled layers, bottom of the stack first.
+	pub(crate) fn update(&mut self, context: &mut LayerContext) {
+		for (layer_id, layer) in self.ids.iter().zip(&mut self.data) {
+			if !self.disabled.contains(layer_id) {
+				self.timings.measure(*layer_id, || layer.on_update(context));
+			}
+		}
+
+		self.tick_elapsed += context.delta_time().duration();
+		if self.tick_elapsed >= self.tick_interval {
+			self.tick_elapsed -= self.tick_interval;
+			for (layer_id, layer) in self.ids.iter().zip(&mut self.data) {
+				if !self.disabled.contains(layer_id) {
+					self.timings.measure(*layer_id, || layer.on_tick(context));
+				}
+			}
+		}
+	}
+
+	/// Renders the enabled layers, bottom of the stack first.
+	pub(crate) fn render(&mut self, context: &LayerContext, render_pass: &mut RenderPass) {
+		for (layer_id, layer) in self.ids.iter().zip(&mut self.data) {
+			if !self.disabled.contains(layer_id) {
+				self.timings.measure(*layer_id, || layer.on_render(context, render_pass));
+			}
+		}
+	}
+
+	/// Passes the event down the enabled layers until one handles it, skipping layers whose event
+	/// mask excludes it. Returns whether a layer handled the event.
+	pub(crate) fn dispatch(&mut self, event_loop: &ActiveEventLoop, event: &Event) -> bool {
+		self.propagate(event, |layer| layer.on_event(event_loop, event))
+	}
+
+	/// The propagation of [`LayerStack::dispatch`], with `on_event` standing in for
+	/// [`Layer::on_event`], which needs a running event loop.
+	fn propagate(
+		&mut self,
+		event: &Event,
+		mut on_event: impl FnMut(&mut dyn Layer) -> EventResult,
+	) -> bool {
+		let category = event.category();
+		for (layer_id, layer) in self.ids.iter().zip(&mut self.data).rev() {
+			if self.disabled.contains(layer_id) || !layer.event_mask().intersects(category) {
+				continue;
+			}
+			if self.timings.measure(*layer_id, || on_event(layer.as_mut())).is_handled() {
+				return true;
+			}
+		}
+		false
+	}
+
+	/// The time each layer spent in its callbacks since the previous call, top of the stack first.
+	/// Always empty without the `profile` feature.
+	pub(crate) fn take_timings(&mut self) -> Vec<(LayerId, String, Duration)> {
+		self.ids
+			.iter()
+			.zip(&self.data)
+			.rev()
+			.filter_map(|(layer_id, layer)| {
+				let duration = self.timings.take(*layer_id)?;
+				Some((*layer_id, layer.name().to_string(), duration))
+			})
+			.collect()
+	}
+}
+
+impl<'data> IntoIterator for &'data LayerStack {
+	type IntoIter = Iter<'data>;
+	type Item = &'data dyn Layer;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
 }
 
 impl<'data> IntoIterator for &'data mut LayerStack {
-	type Item = &'data mut dyn Layer;
 	type IntoIter = IterMut<'data>;
+	type Item = &'data mut dyn Layer;
 
 	fn into_iter(self) -> Self::IntoIter {
 		self.iter_mut()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::{cell::RefCell, rc::Rc, time::Duration};
+
+	use wgpu::RenderPass;
+
+	use super::{EventResult, Layer, LayerStack};
+	use crate::{
+		context::LayerContext,
+		event::{Event, EventCategory},
+		geometry::Size,
+		Error, Harness,
+	};
+
+	struct Named {
+		name: &'static str,
+		event_mask: EventCategory,
+	}
+
+	fn named(name: &'static str) -> Named {
+		Named { name, event_mask: EventCategory::all() }
+	}
+
+	impl Layer for Named {
+		fn name(&self) -> &str {
+			self.name
+		}
+
+		fn event_mask(&self) -> EventCategory {
+			self.event_mask
+		}
+	}
+
+	/// Layers `a` and `b` below overlays `x` and `y`.
+	fn stack() -> LayerStack {
+		let mut layer_stack = LayerStack::new();
+		layer_stack.push_overlay(named("x"));
+		layer_stack.push_layer(named("a"));
+		layer_stack.push_overlay(named("y"));
+		layer_stack.push_layer(named("b"));
+		layer_stack
+	}
+
+	/// The names of the layers, bottom of the stack first, and how many are layers, not overlays.
+	fn order(layer_stack: &LayerStack) -> (Vec<&str>, usize) {
+		(layer_stack.data.iter().map(|it| it.name()).collect(), layer_stack.layer_count())
+	}
+
+	/// Propagates `event`, with layers returning the result given for their name or `Pass`.
+	/// Returns the names of the layers that saw the event, in order, and whether it was handled.
+	fn propagate(
+		layer_stack: &mut LayerStack,
+		event: &Event,
+		results: &[(&str, EventResult)],
+	) -> (Vec<String>, bool) {
+		let mut seen = Vec::new();
+		let handled = layer_stack.propagate(event, |layer| {
+			seen.push(layer.name().to_string());
+			results
+				.iter()
+				.find(|(name, _)| *name == layer.name())
+				.map_or(EventResult::Pass, |it| it.1)
+		});
+		(seen, handled)
+	}
+
+	#[test]
+	fn events_reach_overlays_first_top_down() {
+		let mut layer_stack = stack();
+		let results = [("y", EventResult::Observe), ("b", EventResult::Observe)];
+		let (seen, handled) = propagate(&mut layer_stack, &Event::KeyTyped('a'), &results);
+		assert_eq!(seen, ["y", "x", "b", "a"]);
+		assert!(!handled);
+	}
+
+	#[test]
+	fn handled_events_stop_propagating() {
+		let mut layer_stack = stack();
+		let results = [("y", EventResult::Observe), ("x", EventResult::Handled)];
+		let (seen, handled) = propagate(&mut layer_stack, &Event::KeyTyped('a'), &results);
+		assert_eq!(seen, ["y", "x"]);
+		assert!(handled);
+
+		let results = [("b", EventResult::Handled)];
+		let (seen, handled) = propagate(&mut layer_stack, &Event::KeyTyped('a'), &results);
+		assert_eq!(seen, ["y", "x", "b"]);
+		assert!(handled);
+	}
+
+	#[test]
+	fn moves_stay_within_the_region() {
+		let mut layer_stack = stack();
+		let [a, b, x, y] = layer_stack.ids[..]
+		else {
+			unreachable!()
+		};
+
+		assert!(layer_stack.move_to_front(a));
+		assert_eq!(order(&layer_stack), (vec!["b", "a", "x", "y"], 2));
+		assert!(layer_stack.move_to_back(y));
+		assert_eq!(order(&layer_stack), (vec!["b", "a", "y", "x"], 2));
+		assert!(layer_stack.move_to_back(b));
+		assert!(layer_stack.move_to_front(x));
+		assert_eq!(order(&layer_stack), (vec!["b", "a", "y", "x"], 2));
+	}
+
+	#[test]
+	fn swaps_stay_within_the_region() {
+		let mut layer_stack = stack();
+		let [a, b, x, y] = layer_stack.ids[..]
+		else {
+			unreachable!()
+		};
+
+		assert!(layer_stack.swap(a, b));
+		assert!(layer_stack.swap(y, x));
+		assert_eq!(order(&layer_stack), (vec!["b", "a", "y", "x"], 2));
+
+		assert!(!layer_stack.swap(a, x));
+		assert!(!layer_stack.swap(y, b));
+		assert_eq!(order(&layer_stack), (vec!["b", "a", "y", "x"], 2));
+	}
+
+	#[test]
+	fn reordering_stale_ids_fails() {
+		let mut layer_stack = stack();
+		let [a, b, x, _] = layer_stack.ids[..]
+		else {
+			unreachable!()
+		};
+		assert!(layer_stack.pop_layer(a).is_some());
+
+		assert!(!layer_stack.move_to_front(a));
+		assert!(!layer_stack.move_to_back(a));
+		assert!(!layer_stack.swap(a, b));
+		assert!(!layer_stack.swap(x, a));
+		assert!(!layer_stack.set_enabled(a, false));
+		assert_eq!(order(&layer_stack), (vec!["b", "x", "y"], 1));
+	}
+
+	#[test]
+	fn insertion_relative_to_layers() {
+		let mut layer_stack = stack();
+		let [a, b, ..] = layer_stack.ids[..]
+		else {
+			unreachable!()
+		};
+
+		assert!(layer_stack.insert_layer_before(a, named("a-")).is_some());
+		assert!(layer_stack.insert_layer_after(b, named("b+")).is_some());
+		assert!(layer_stack.insert_layer_after(a, named("a+")).is_some());
+		assert_eq!(order(&layer_stack), (vec!["a-", "a", "a+", "b", "b+", "x", "y"], 5));
+	}
+
+	#[test]
+	fn insertion_relative_to_overlays() {
+		let mut layer_stack = stack();
+		let [.., x, y] = layer_stack.ids[..]
+		else {
+			unreachable!()
+		};
+
+		assert!(layer_stack.insert_overlay_before(x, named("x-")).is_some());
+		assert!(layer_stack.insert_overlay_after(y, named("y+")).is_some());
+		assert!(layer_stack.insert_overlay_after(x, named("x+")).is_some());
+		assert_eq!(order(&layer_stack), (vec!["a", "b", "x-", "x", "x+", "y", "y+"], 2));
+	}
+
+	#[test]
+	fn insertion_keeps_the_partition() {
+		let mut layer_stack = stack();
+		let [a, b, x, y] = layer_stack.ids[..]
+		else {
+			unreachable!()
+		};
+
+		// the anchor must be on the same side, so the first layer never lands above an overlay
+		assert_eq!(layer_stack.insert_layer_after(x, named("l")), None);
+		assert_eq!(layer_stack.insert_layer_before(y, named("l")), None);
+		assert_eq!(layer_stack.insert_overlay_before(a, named("o")), None);
+		assert_eq!(layer_stack.insert_overlay_after(b, named("o")), None);
+		assert_eq!(order(&layer_stack), (vec!["a", "b", "x", "y"], 2));
+
+		// inserting at the boundary keeps layers below overlays
+		assert!(layer_stack.insert_layer_after(b, named("b+")).is_some());
+		assert!(layer_stack.insert_overlay_before(x, named("x-")).is_some());
+		assert_eq!(order(&layer_stack), (vec!["a", "b", "b+", "x-", "x", "y"], 3));
+	}
+
+	#[test]
+	fn insertion_relative_to_unknown_ids_fails() {
+		let mut layer_stack = stack();
+		let a = layer_stack.ids[0];
+		assert!(layer_stack.pop_layer(a).is_some());
+
+		assert_eq!(layer_stack.insert_layer_before(a, named("l")), None);
+		assert_eq!(layer_stack.insert_layer_after(a, named("l")), None);
+		assert_eq!(layer_stack.insert_overlay_before(a, named("o")), None);
+		assert_eq!(layer_stack.insert_overlay_after(a, named("o")), None);
+		assert_eq!(order(&layer_stack), (vec!["b", "x", "y"], 1));
+	}
+
+	#[test]
+	fn iteration_goes_top_down() {
+		let mut layer_stack = stack();
+		let ids = layer_stack.ids.clone();
+
+		let names: Vec<_> = layer_stack.iter().map(Layer::name).collect();
+		assert_eq!(names, ["y", "x", "b", "a"]);
+
+		let names: Vec<_> = layer_stack.iter_mut().map(|it| it.name().to_string()).collect();
+		assert_eq!(names, ["y", "x", "b", "a"]);
+
+		let with_ids: Vec<_> = layer_stack.iter_mut_with_ids().map(|(id, _)| id).collect();
+		assert_eq!(with_ids, [ids[3], ids[2], ids[1], ids[0]]);
+	}
+
+	/// Logs its name when rendered.
+	struct Drawn(&'static str, Rc<RefCell<Vec<&'static str>>>);
+
+	impl Layer for Drawn {
+		fn name(&self) -> &'static str {
+			self.0
+		}
+
+		fn on_render(&mut self, _context: &LayerContext, _render_pass: &mut RenderPass) {
+			self.1.borrow_mut().push(self.0);
+		}
+	}
+
+	#[test]
+	fn rendering_goes_bottom_up() {
+		let log = Rc::new(RefCell::new(Vec::new()));
+		let harness = Harness::new(
+			(),
+			|layer_stack| {
+				layer_stack.push_overlay(Drawn("x", Rc::clone(&log)));
+				layer_stack.push_layer(Drawn("a", Rc::clone(&log)));
+				layer_stack.push_overlay(Drawn("y", Rc::clone(&log)));
+				layer_stack.push_layer(Drawn("b", Rc::clone(&log)));
+			},
+			Size::new(1, 1),
+		);
+		let mut harness = match harness {
+			Ok(harness) => harness,
+			Err(Error::NoAdapter) => return,
+			Err(error) => panic!("{error:?}"),
+		};
+
+		harness.tick(Duration::from_millis(16));
+		assert_eq!(*log.borrow(), ["a", "b", "x", "y"]);
+	}
+
+	#[test]
+	fn masked_layers_skip_filtered_categories() {
+		let mut layer_stack = LayerStack::new();
+		layer_stack.push_layer(named("all"));
+		layer_stack.push_layer(Named { name: "mouse", event_mask: EventCategory::MOUSE });
+		layer_stack.push_overlay(Named { name: "keyboard", event_mask: EventCategory::KEYBOARD });
+		layer_stack.push_overlay(Named { name: "none", event_mask: EventCategory::empty() });
+
+		let (seen, _) = propagate(&mut layer_stack, &Event::KeyTyped('a'), &[]);
+		assert_eq!(seen, ["keyboard", "all"]);
+
+		let (seen, _) = propagate(&mut layer_stack, &Event::MouseMoved { x: 1.0, y: 2.0 }, &[]);
+		assert_eq!(seen, ["mouse", "all"]);
+
+		let (seen, _) = propagate(&mut layer_stack, &Event::WindowClose, &[]);
+		assert_eq!(seen, ["all"]);
+	}
+
+	/// Logs its updates and renders as `"update"` and `"render"`.
+	struct Logged(Rc<RefCell<Vec<&'static str>>>);
+
+	impl Layer for Logged {
+		fn name(&self) -> &'static str {
+			"Logged"
+		}
+
+		fn on_update(&mut self, _context: &mut LayerContext) {
+			self.0.borrow_mut().push("update");
+		}
+
+		fn on_render(&mut self, _context: &LayerContext, _render_pass: &mut RenderPass) {
+			self.0.borrow_mut().push("render");
+		}
+	}
+
+	#[test]
+	fn disabled_layers_skip_updates_and_renders() {
+		let log = Rc::new(RefCell::new(Vec::new()));
+		let harness = Harness::new(
+			(),
+			|layer_stack| {
+				layer_stack.push_layer(Logged(Rc::clone(&log)));
+			},
+			Size::new(1, 1),
+		);
+		let mut harness = match harness {
+			Ok(harness) => harness,
+			Err(Error::NoAdapter) => return,
+			Err(error) => panic!("{error:?}"),
+		};
+		let frame = Duration::from_millis(16);
+		let layer_id = harness.layer_stack().ids[0];
+
+		assert!(harness.layer_stack_mut().set_enabled(layer_id, false));
+		harness.tick(frame);
+		assert!(log.borrow().is_empty());
+
+		assert!(harness.layer_stack_mut().set_enabled(layer_id, true));
+		harness.tick(frame);
+		assert_eq!(*log.borrow(), ["update", "render"]);
+	}
+
+	#[test]
+	fn disabled_layers_skip_events() {
+		let mut layer_stack = stack();
+		let b = layer_stack.ids[1];
+		let y = layer_stack.ids[3];
+
+		assert!(layer_stack.set_enabled(y, false));
+		assert!(layer_stack.set_enabled(b, false));
+		assert!(!layer_stack.is_enabled(b));
+		let (seen, _) = propagate(&mut layer_stack, &Event::KeyTyped('a'), &[]);
+		assert_eq!(seen, ["x", "a"]);
+
+		assert!(layer_stack.set_enabled(b, true));
+		assert!(layer_stack.is_enabled(b));
+		let (seen, _) = propagate(&mut layer_stack, &Event::KeyTyped('a'), &[]);
+		assert_eq!(seen, ["x", "b", "a"]);
+	}
+}
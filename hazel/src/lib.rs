@@ -1,144 +1,703 @@
+pub mod assets;
+#[cfg(feature = "audio")]
+pub mod audio;
+mod color;
+pub mod context;
 pub mod event;
+mod exit;
+mod gamepad;
+pub mod geometry;
+mod gpu;
+mod headless;
+pub mod input;
+mod keyboard;
 pub mod layer;
 pub mod log;
+pub mod math;
+pub mod monitor;
+#[cfg(feature = "net")]
+pub mod net;
+#[cfg(feature = "profile")]
+pub mod profile;
+pub mod record;
+pub mod renderer;
+pub mod screenshot;
+mod spec;
+pub mod time;
+mod translate;
+pub mod util;
 
-use std::sync::Arc;
+/// Times the rest of the enclosing scope. Does nothing without the `profile` feature.
+#[cfg(not(feature = "profile"))]
+#[macro_export]
+macro_rules! profile_scope {
+	($name:expr) => {};
+}
+
+use std::{
+	path::{Path, PathBuf},
+	sync::{mpsc::Receiver, Arc},
+	time::{Duration, Instant},
+};
 
-use pollster::FutureExt;
 use tap::Pipe;
 use wgpu::{
-	Color, CommandEncoderDescriptor, Device, DeviceDescriptor, Features, Instance, Limits, LoadOp,
-	MemoryHints, Operations, PowerPreference, Queue, RenderPassColorAttachment,
-	RenderPassDescriptor, RequestAdapterOptions, StoreOp, Surface, SurfaceConfiguration,
-	TextureViewDescriptor,
+	Adapter, CommandBuffer, CommandEncoderDescriptor, CompositeAlphaMode, Device, Features,
+	Instance, LoadOp, Operations, PresentMode, Queue, RenderPassColorAttachment,
+	RenderPassDepthStencilAttachment, RenderPassDescriptor, RequestDeviceError, StoreOp, Surface,
+	SurfaceConfiguration, SurfaceError, TextureUsages, TextureView, TextureViewDescriptor,
 };
 use winit::{
 	application::ApplicationHandler,
-	dpi::{PhysicalPosition, PhysicalSize},
+	dpi::PhysicalSize,
 	error::EventLoopError,
-	event::{ElementState, MouseScrollDelta, WindowEvent},
-	event_loop::EventLoop,
-	window::{Window, WindowId},
+	event::{DeviceEvent, DeviceId, ElementState, Ime, WindowEvent},
+	event_loop::{ControlFlow, EventLoop},
+	window::{Window, WindowId, WindowLevel},
 };
-pub use winit::{event::MouseButton, event_loop::ActiveEventLoop, keyboard::Key};
+pub use winit::{event::MouseButton, event_loop::ActiveEventLoop, window::Fullscreen};
 
 #[allow(unused)]
 pub(crate) use crate::log::{core_debug, core_error, core_info, core_trace, core_warn};
-use crate::{event::Event, layer::LayerStack};
+use crate::{
+	assets::AssetManager,
+	context::{LayerContext, RenderSettings, SurfaceContext, WindowContext},
+	event::{Event, EventQueue, UserEvent},
+	geometry::Size,
+	gpu::{GpuTimer, RenderTargets},
+	input::{Input, Modifiers},
+	layer::{LayerId, LayerStack},
+	record::Replay,
+	renderer::RendererConfig,
+	screenshot::Screenshots,
+	time::{FixedTimestep, FrameStats},
+};
+pub use crate::{
+	color::Color,
+	exit::ExitReason,
+	gamepad::{GamepadAxis, GamepadButton, GamepadId},
+	headless::{run_headless, Harness},
+	keyboard::KeyCode,
+	spec::{RedrawPolicy, WindowSize, WindowSpec},
+	time::{is_reduced_motion, set_reduced_motion},
+};
 
 pub trait Application {
-	fn on_key_pressed(&mut self, _event_loop: &ActiveEventLoop, _key: &Key, _is_repeat: bool) {}
-	fn on_key_released(&mut self, _event_loop: &ActiveEventLoop, _key: &Key) {}
+	fn on_key_pressed(&mut self, _event_loop: &ActiveEventLoop, _key: KeyCode, _is_repeat: bool) {}
+	fn on_key_released(&mut self, _event_loop: &ActiveEventLoop, _key: KeyCode) {}
+	fn on_key_typed(&mut self, _event_loop: &ActiveEventLoop, _character: char) {}
+	fn on_modifiers_changed(&mut self, _event_loop: &ActiveEventLoop, _modifiers: Modifiers) {}
 	fn on_mouse_button_pressed(&self, _event_loop: &ActiveEventLoop, _button: &MouseButton) {}
 	fn on_mouse_button_released(&self, _event_loop: &ActiveEventLoop, _button: &MouseButton) {}
+	/// Called with the raw code of buttons beyond the named ones, e.g. extra side buttons, after
+	/// `on_mouse_button_pressed` with [`MouseButton::Other`].
+	fn on_other_mouse_button_pressed(&self, _event_loop: &ActiveEventLoop, _code: u16) {}
+	fn on_other_mouse_button_released(&self, _event_loop: &ActiveEventLoop, _code: u16) {}
 	fn on_mouse_moved(&self, _event_loop: &ActiveEventLoop, _x: f32, _y: f32) {}
+	/// Called with unaccelerated mouse deltas that are not bound by the window, for camera
+	/// control. Only delivered while the window has focus.
+	fn on_mouse_motion_raw(&self, _event_loop: &ActiveEventLoop, _dx: f32, _dy: f32) {}
 	fn on_mouse_scrolled(&self, _event_loop: &ActiveEventLoop, _x_offset: f32, _y_offset: f32) {}
 	fn on_window_close(&self, event_loop: &ActiveEventLoop) {
 		event_loop.exit();
 	}
+	/// Called with the new size in physical pixels. Divide by the window's scale factor, passed
+	/// alongside in [`Event::WindowResize`], for the logical size.
 	fn on_window_resize(&self, _event_loop: &ActiveEventLoop, _width: u32, _height: u32) {}
+	fn on_window_scale_changed(&self, _event_loop: &ActiveEventLoop, _scale_factor: f64) {}
+	/// Called for every file dropped on the window, one call per file when several are dropped.
+	fn on_file_dropped(&mut self, _event_loop: &ActiveEventLoop, _path: &Path) {}
+	fn on_file_hovered(&mut self, _event_loop: &ActiveEventLoop, _path: &Path) {}
+	fn on_file_hover_cancelled(&mut self, _event_loop: &ActiveEventLoop) {}
+	/// Called with events a peer sent through a `NetLayer`, after the frame they arrived in.
+	fn on_remote_event(&mut self, _event_loop: &ActiveEventLoop, _event: &Event) {}
+	fn on_gamepad_connected(&mut self, _event_loop: &ActiveEventLoop, _id: GamepadId) {}
+	fn on_gamepad_disconnected(&mut self, _event_loop: &ActiveEventLoop, _id: GamepadId) {}
+	fn on_gamepad_button_pressed(
+		&mut self,
+		_event_loop: &ActiveEventLoop,
+		_id: GamepadId,
+		_button: GamepadButton,
+	) {
+	}
+	fn on_gamepad_button_released(
+		&mut self,
+		_event_loop: &ActiveEventLoop,
+		_id: GamepadId,
+		_button: GamepadButton,
+	) {
+	}
+	fn on_gamepad_axis_moved(
+		&mut self,
+		_event_loop: &ActiveEventLoop,
+		_id: GamepadId,
+		_axis: GamepadAxis,
+		_value: f32,
+	) {
+	}
+	/// Called with the payloads posted through an [`event::EventQueue`].
+	fn on_user_event(&mut self, _event_loop: &ActiveEventLoop, _event: &UserEvent) {}
+
+	/// Whether the main loop should stop redrawing and updating layers while the window is
+	/// minimized or unfocused. Override to keep ticking in the background.
+	fn pause_when_inactive(&self) -> bool {
+		true
+	}
+
+	/// Called once the window and GPU device are ready, before the first layer update, e.g. to
+	/// load the initial level.
+	fn on_start(&mut self, _context: &mut LayerContext) {}
+
+	/// Called once when the event loop exits, after the last frame, e.g. to save. Not called if
+	/// initialization failed.
+	fn on_stop(&mut self, _context: &mut LayerContext) {}
+}
+
+/// An application without state, for when all the logic lives in layers.
+impl Application for () {}
+
+/// Builds the [`LayerContext`] of a window from the fields of a [`Context`] and its [`State`].
+/// The fields are borrowed one by one, so the layer stack and the application stay free to be
+/// called with the context.
+macro_rules! window_layer_context {
+	($context:ident, $state:ident) => {
+		LayerContext {
+			adapter: &$state.adapter,
+			device: &$state.device,
+			queue: &$state.queue,
+			renderer_config: $state.renderer_config,
+			size: Size::new($state.config.width, $state.config.height),
+			scale_factor: $state.window.scale_factor(),
+			window: Some(WindowContext {
+				window: &$state.window,
+				windowed_size: &mut $state.windowed_size,
+			}),
+			frame_stats: &$context.frame_stats,
+			render_settings: &mut $context.render_settings,
+			input: &$context.input,
+			assets: &mut $state.assets,
+			#[cfg(feature = "audio")]
+			audio: &mut $context.audio,
+			replay: &mut $context.replay,
+			layer_timings: &$context.layer_timings,
+			exit_reason: &mut $context.exit_reason,
+			redraw_deadline: &mut $context.redraw_deadline,
+			event_queue: &$context.event_queue,
+			screenshot_path: &mut $context.screenshot_path,
+			fixed_timestep: $context.fixed_timestep.map(FixedTimestep::step),
+			interpolation_alpha: $context.fixed_timestep.map_or(1.0, FixedTimestep::alpha),
+			#[cfg(feature = "net")]
+			remote_events: &mut $context.remote_events,
+			surface: $state.surface.as_ref().map(|surface| SurfaceContext {
+				surface,
+				config: &mut $state.config,
+				present_modes: &$state.present_modes,
+			}),
+		}
+	};
 }
 
 struct State<'app> {
 	window: Arc<Window>,
-	surface: Surface<'app>,
+	instance: Instance,
+	/// Dropped while the application is suspended.
+	surface: Option<Surface<'app>>,
+	/// Whether the layers still need to be told about a surface recreated on resume.
+	surface_recreated: bool,
+	adapter: Adapter,
 	device: Device,
 	queue: Queue,
 	config: SurfaceConfiguration,
+	present_modes: Vec<PresentMode>,
+	renderer_config: RendererConfig,
+	targets: RenderTargets,
+	/// `None` without `TIMESTAMP_QUERY`.
+	gpu_timer: Option<GpuTimer>,
+	/// Screenshots still being read back from the GPU or written.
+	screenshots: Screenshots,
+	assets: AssetManager,
+	/// The size to restore when leaving fullscreen.
+	windowed_size: Option<PhysicalSize<u32>>,
 }
 
 pub struct Context<'app, App: Application> {
 	application: App,
+	spec: WindowSpec,
 	layer_stack: LayerStack,
+	frame_stats: FrameStats,
+	render_settings: RenderSettings,
+	input: Input,
+	focused: bool,
+	minimized: bool,
+	#[cfg(feature = "gamepad")]
+	gilrs: Option<gilrs::Gilrs>,
+	#[cfg(feature = "audio")]
+	audio: audio::Audio,
+	replay: Replay,
+	/// Per-layer timings of the previous frame.
+	layer_timings: Vec<(LayerId, String, Duration)>,
+	/// The earliest redraw requested by [`LayerContext::request_redraw_in`].
+	redraw_deadline: Option<Instant>,
+	event_queue: EventQueue,
+	posted_events: Receiver<Event>,
+	/// Where to save a screenshot of the current frame, see [`LayerContext::capture_screenshot`].
+	screenshot_path: Option<PathBuf>,
+	/// `None` without [`WindowSpec::fixed_timestep`].
+	fixed_timestep: Option<FixedTimestep>,
+	/// The latest `MouseMoved` held back by [`WindowSpec::coalesce_mouse_moves`].
+	pending_mouse_move: Option<Event>,
+	#[cfg(feature = "net")]
+	remote_events: Vec<Event>,
 	state: Option<State<'app>>,
+	/// Why the application exits. Recorded as soon as the exit is requested, also by
+	/// [`LayerContext::request_exit`], which is honored at the end of the frame.
+	exit_reason: Option<ExitReason>,
+	error: Option<Error>,
+}
+
+impl State<'_> {
+	/// Creates a new surface for the window after a suspend, keeping the device and everything
+	/// else. The window may have been resized in the meantime.
+	fn recreate_surface(&mut self) -> Result<(), Error> {
+		if self.surface.is_some() {
+			return Ok(());
+		}
+		let surface = self
+			.instance
+			.create_surface(self.window.clone())
+			.map_err(|error| Error::Unknown(format!("Could not create surface: {error}")))?;
+		let PhysicalSize { width, height } = self.window.inner_size();
+		if width != 0 && height != 0 && (width, height) != (self.config.width, self.config.height) {
+			self.config.width = width;
+			self.config.height = height;
+			self.targets =
+				RenderTargets::new(&self.device, &self.renderer_config, Size::new(width, height));
+		}
+		surface.configure(&self.device, &self.config);
+		self.surface = Some(surface);
+		self.surface_recreated = true;
+		Ok(())
+	}
 }
 
 impl<'app, App: Application> Context<'app, App> {
-	fn new(application: App, layer_setup: impl Fn(&mut LayerStack)) -> Self {
+	fn new(application: App, mut spec: WindowSpec, layer_setup: impl Fn(&mut LayerStack)) -> Self {
+		// a zero step would never consume the lag, so fall back to one update per frame
+		spec.fixed_timestep = spec.fixed_timestep.filter(|step| !step.is_zero());
 		let mut layer_stack = LayerStack::new();
 		layer_setup(&mut layer_stack);
-		Context { application, layer_stack, state: None }
+		let (event_queue, posted_events) = EventQueue::new();
+		let fixed_timestep = spec.fixed_timestep.map(FixedTimestep::new);
+		Context {
+			application,
+			spec,
+			layer_stack,
+			frame_stats: FrameStats::new(),
+			render_settings: RenderSettings::default(),
+			input: Input::default(),
+			focused: true,
+			minimized: false,
+			#[cfg(feature = "gamepad")]
+			gilrs: gilrs::Gilrs::new()
+				.inspect_err(|error| core_warn!("Gamepad support unavailable: {error}"))
+				.ok(),
+			#[cfg(feature = "audio")]
+			audio: audio::Audio::new(),
+			replay: Replay::default(),
+			layer_timings: Vec::new(),
+			redraw_deadline: None,
+			event_queue,
+			posted_events,
+			screenshot_path: None,
+			fixed_timestep,
+			pending_mouse_move: None,
+			#[cfg(feature = "net")]
+			remote_events: Vec::new(),
+			state: None,
+			exit_reason: None,
+			error: None,
+		}
+	}
+
+	fn create_state(&self, event_loop: &ActiveEventLoop) -> Result<State<'app>, Error> {
+		let attributes = Window::default_attributes()
+			.with_title(&self.spec.title)
+			.with_decorations(self.spec.decorations)
+			.with_transparent(self.spec.transparent)
+			.with_window_level(
+				if self.spec.always_on_top {
+					WindowLevel::AlwaysOnTop
+				}
+				else {
+					WindowLevel::Normal
+				},
+			);
+		// Wayland has no primary monitor, take the first one to size the window by instead
+		let monitor =
+			event_loop.primary_monitor().or_else(|| event_loop.available_monitors().next());
+		let window = event_loop
+			.create_window(self.spec.size.apply(attributes, monitor.as_ref()))
+			.map_err(|error| Error::Unknown(format!("Could not create window: {error}")))?
+			.pipe(Arc::new);
+
+		let (instance, surface, adapter) = gpu::with_backend_fallback(&self.spec, |instance| {
+			let surface = instance
+				.create_surface(window.clone())
+				.map_err(|error| Error::Unknown(format!("Could not create surface: {error}")))?;
+			let adapter = gpu::request_adapter(&instance, &self.spec, Some(&surface))?;
+			Ok((instance, surface, adapter))
+		})?;
+		let (device, queue) = gpu::request_device(&adapter, &self.spec)?;
+
+		let capabilities = surface.get_capabilities(&adapter);
+		let config = {
+			// inner_size is already in physical pixels, the size of the framebuffer, so it must not
+			// be scaled by the scale factor again. Logical sizes are only derived for layers.
+			let PhysicalSize { width, height } = window.inner_size();
+			let mut config = surface
+				.get_default_config(&adapter, width, height)
+				.ok_or_else(|| Error::Unknown("Surface is not supported by the adapter".into()))?;
+			if let Some(format) =
+				gpu::choose_surface_format(&capabilities.formats, self.spec.surface_format)
+			{
+				config.format = format;
+			}
+			// allows reading frames back for screenshots
+			if capabilities.usages.contains(TextureUsages::COPY_SRC) {
+				config.usage |= TextureUsages::COPY_SRC;
+			}
+			config.alpha_mode = gpu::choose_alpha_mode(
+				&capabilities.alpha_modes,
+				config.alpha_mode,
+				self.spec.transparent,
+			);
+			config
+		};
+		let format = config.format;
+		core_info!("Using surface format {format:?}");
+		surface.configure(&device, &config);
+		let present_modes = capabilities.present_modes;
+		let sample_count = gpu::supported_sample_count(
+			&adapter,
+			config.format,
+			self.spec.depth_compare.is_some(),
+			self.spec.sample_count,
+		);
+		let renderer_config =
+			RendererConfig { format, sample_count, depth_compare: self.spec.depth_compare };
+		let assets = AssetManager::new(&device, &queue);
+		let targets =
+			RenderTargets::new(&device, &renderer_config, Size::new(config.width, config.height));
+		let gpu_timer = GpuTimer::new(&device, &queue);
+
+		Ok(State {
+			window,
+			instance,
+			surface: Some(surface),
+			surface_recreated: false,
+			adapter,
+			device,
+			queue,
+			config,
+			present_modes,
+			renderer_config,
+			targets,
+			gpu_timer,
+			screenshots: Screenshots::default(),
+			assets,
+			windowed_size: None,
+		})
+	}
+
+	/// When to wake up to poll for gamepad events, if gamepad support is available.
+	#[cfg(feature = "gamepad")]
+	fn gamepad_poll(&self) -> Option<Instant> {
+		self.gilrs.as_ref().map(|gilrs| Instant::now() + gamepad::poll_interval(gilrs))
 	}
 
+	#[cfg(not(feature = "gamepad"))]
+	#[allow(clippy::unused_self)]
+	fn gamepad_poll(&self) -> Option<Instant> {
+		None
+	}
+
+	fn is_paused(&self) -> bool {
+		self.application.pause_when_inactive() && (self.minimized || !self.focused)
+	}
+
+	/// Dispatches a `KeyTyped` event for every printable character of `text`.
+	fn on_text(&mut self, event_loop: &ActiveEventLoop, text: &str) {
+		for event in translate::typed_text(text) {
+			self.on_event(event_loop, &event);
+		}
+	}
+
+	fn redraw(&mut self, event_loop: &ActiveEventLoop) {
+		crate::profile_scope!("redraw");
+		self.flush_mouse_move(event_loop);
+		if self.minimized {
+			return;
+		}
+		let paused = self.is_paused();
+		let Some(state) = self.state.as_mut()
+		else {
+			return;
+		};
+		if state.surface.is_none() {
+			return;
+		}
+		let surface_recreated = std::mem::take(&mut state.surface_recreated);
+
+		self.frame_stats.begin_frame();
+		if let Some(gpu_time) = state.gpu_timer.as_mut().and_then(|it| it.collect(&state.device)) {
+			self.frame_stats.set_gpu_frame_time(gpu_time);
+		}
+		state.screenshots.save_finished(&state.device);
+		self.layer_timings = self.layer_stack.take_timings();
+		state.assets.upload_decoded(&state.device, &state.queue);
+		if surface_recreated {
+			self.layer_stack.surface_recreated(&window_layer_context!(self, state));
+		}
+		let mut updated = false;
+		if !paused {
+			let frame_time = self.frame_stats.delta_time().duration();
+			if let Some(steps) = self.fixed_timestep.as_mut().map(|it| it.advance(frame_time)) {
+				// end presses after each step, so catch-up steps do not see them again
+				for _ in 0..steps {
+					update_layers(&mut self.layer_stack, &mut window_layer_context!(self, state));
+					self.input.end_update();
+				}
+			}
+			else {
+				update_layers(&mut self.layer_stack, &mut window_layer_context!(self, state));
+				updated = true;
+			}
+		}
+		let context = window_layer_context!(self, state);
+
+		// the surface may have been reconfigured during the update, so only acquire the frame now.
+		// Skipped frames still end the update below, so input and exit requests are not lost.
+		let frame = match state.surface.as_ref().map(Surface::get_current_texture) {
+			None => None,
+			Some(Ok(frame)) => Some(frame),
+			Some(Err(SurfaceError::Lost | SurfaceError::Outdated)) => {
+				core_warn!("Surface lost or outdated, reconfiguring");
+				// through the context, which holds the configuration for the rest of the frame
+				if let Some(surface) = &context.surface {
+					surface.surface.configure(&state.device, surface.config);
+				}
+				state.window.request_redraw();
+				None
+			},
+			Some(Err(SurfaceError::Timeout)) => {
+				core_warn!("Timed out acquiring the next frame, skipping it");
+				state.window.request_redraw();
+				None
+			},
+			Some(Err(SurfaceError::OutOfMemory)) => {
+				core_error!("Out of memory acquiring the next frame");
+				self.error.replace(Error::OutOfMemory);
+				self.exit_reason = Some(ExitReason::Error);
+				event_loop.exit();
+				return;
+			},
+		};
+		if let Some(frame) = frame {
+			let view = frame.texture.create_view(&TextureViewDescriptor::default());
+			let command_buffer = render_layers(
+				&mut self.layer_stack,
+				&context,
+				&view,
+				&state.targets,
+				state.gpu_timer.as_ref(),
+			);
+			state.queue.submit(Some(command_buffer));
+			if let Some(gpu_timer) = &mut state.gpu_timer {
+				gpu_timer.map_readback();
+			}
+			if let Some(path) = self.screenshot_path.take() {
+				if frame.texture.usage().contains(TextureUsages::COPY_SRC) {
+					state.screenshots.capture(&state.device, &state.queue, &frame.texture, path);
+				}
+				else {
+					core_warn!("The surface does not support screenshots");
+				}
+			}
+			frame.present();
+			// keep frames coming until the screenshots are read back, also when drawing on demand
+			if state.screenshots.is_reading_back() {
+				state.window.request_redraw();
+			}
+
+			limit_frame_rate(
+				self.spec.frame_rate_limit,
+				state.config.present_mode,
+				&self.frame_stats,
+			);
+		}
+
+		self.end_frame(event_loop, updated);
+	}
+
+	/// The bookkeeping after the updates of a frame, whether or not it was presented.
+	fn end_frame(&mut self, event_loop: &ActiveEventLoop, updated: bool) {
+		// keep presses of a frame without an update around for the next one
+		if updated {
+			self.input.end_update();
+		}
+
+		#[cfg(feature = "net")]
+		for event in std::mem::take(&mut self.remote_events) {
+			self.application.on_remote_event(event_loop, &event);
+		}
+
+		// a layer requested the exit, the other reasons are set as the event loop exits
+		if self.exit_reason.is_some() {
+			event_loop.exit();
+		}
+	}
+
+	/// Dispatches a live event, unless playback of a recording overrides it.
 	fn on_event(&mut self, event_loop: &ActiveEventLoop, event: &Event) {
+		if self.spec.coalesce_mouse_moves && matches!(event, Event::MouseMoved { .. }) {
+			self.pending_mouse_move = Some(event.clone());
+			return;
+		}
+		// keep the held back move ahead of the event that follows it
+		self.flush_mouse_move(event_loop);
+		if self.replay.accepts_live(event) {
+			self.dispatch(event_loop, event);
+		}
+	}
+
+	fn flush_mouse_move(&mut self, event_loop: &ActiveEventLoop) {
+		if let Some(event) = self.pending_mouse_move.take() {
+			if self.replay.accepts_live(&event) {
+				self.dispatch(event_loop, &event);
+			}
+		}
+	}
+
+	fn dispatch(&mut self, event_loop: &ActiveEventLoop, event: &Event) {
+		self.replay.record(event);
+		self.input.handle(event);
+		if self.spec.redraw_policy == RedrawPolicy::OnDemand {
+			if let Some(state) = &self.state {
+				state.window.request_redraw();
+			}
+		}
+
 		match event {
-			Event::KeyPressed { key, is_repeat } => {
-				self.application.on_key_pressed(event_loop, key, *is_repeat);
+			Event::KeyPressed { key, is_repeat, .. } => {
+				self.application.on_key_pressed(event_loop, *key, *is_repeat);
+			},
+			Event::KeyReleased { key, .. } => {
+				self.application.on_key_released(event_loop, *key);
 			},
-			Event::KeyReleased { key } => {
-				self.application.on_key_released(event_loop, key);
+			Event::KeyTyped(character) => {
+				self.application.on_key_typed(event_loop, *character);
+			},
+			Event::ModifiersChanged(modifiers) => {
+				self.application.on_modifiers_changed(event_loop, *modifiers);
 			},
 			Event::MouseButtonPressed(button) => {
 				self.application.on_mouse_button_pressed(event_loop, button);
+				if let MouseButton::Other(code) = button {
+					self.application.on_other_mouse_button_pressed(event_loop, *code);
+				}
 			},
 			Event::MouseButtonReleased(button) => {
 				self.application.on_mouse_button_released(event_loop, button);
+				if let MouseButton::Other(code) = button {
+					self.application.on_other_mouse_button_released(event_loop, *code);
+				}
 			},
 			Event::MouseMoved { x, y } => {
 				self.application.on_mouse_moved(event_loop, *x, *y);
 			},
-			Event::MouseScrolled { x_offset, y_offset } => {
+			Event::MouseMotionRaw { dx, dy } => {
+				self.application.on_mouse_motion_raw(event_loop, *dx, *dy);
+			},
+			Event::MouseScrolled { x_offset, y_offset, .. } => {
 				self.application.on_mouse_scrolled(event_loop, *x_offset, *y_offset);
 			},
 			Event::WindowClose => {
 				self.application.on_window_close(event_loop);
 			},
-			Event::WindowResize { width, height } => {
+			Event::WindowResize { width, height, .. } => {
 				self.application.on_window_resize(event_loop, *width, *height);
 			},
+			Event::WindowScaleChanged { scale_factor } => {
+				self.application.on_window_scale_changed(event_loop, *scale_factor);
+			},
+			Event::FileDropped(path) => {
+				self.application.on_file_dropped(event_loop, path);
+			},
+			Event::FileHovered(path) => {
+				self.application.on_file_hovered(event_loop, path);
+			},
+			Event::FileHoverCancelled => {
+				self.application.on_file_hover_cancelled(event_loop);
+			},
+			Event::GamepadConnected(id) => {
+				self.application.on_gamepad_connected(event_loop, *id);
+			},
+			Event::GamepadDisconnected(id) => {
+				self.application.on_gamepad_disconnected(event_loop, *id);
+			},
+			Event::GamepadButtonPressed { id, button } => {
+				self.application.on_gamepad_button_pressed(event_loop, *id, *button);
+			},
+			Event::GamepadButtonReleased { id, button } => {
+				self.application.on_gamepad_button_released(event_loop, *id, *button);
+			},
+			Event::GamepadAxisMoved { id, axis, value } => {
+				self.application.on_gamepad_axis_moved(event_loop, *id, *axis, *value);
+			},
+			Event::User(payload) => {
+				self.application.on_user_event(event_loop, payload);
+			},
 		}
 
-		for layer in &mut self.layer_stack {
-			if layer.on_event(event_loop, event) {
-				break;
-			}
+		if event_loop.exiting() && self.exit_reason.is_none() {
+			self.exit_reason = Some(match event {
+				Event::WindowClose => ExitReason::UserClosed,
+				_ => ExitReason::ApplicationRequested,
+			});
+		}
+
+		self.layer_stack.dispatch(event_loop, event);
+
+		if event_loop.exiting() && self.exit_reason.is_none() {
+			self.exit_reason = Some(ExitReason::LayerRequested);
 		}
 	}
 }
 
-impl<'app, App: Application> ApplicationHandler for Context<'app, App> {
+impl<App: Application> ApplicationHandler for Context<'_, App> {
 	fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-		let window = event_loop
-			.create_window(Window::default_attributes())
-			.expect("Could not create window!")
-			.pipe(Arc::new);
-
-		let instance = Instance::default();
-		let surface = instance.create_surface(window.clone()).expect("Could not create surface!");
-		let adapter = instance
-			.request_adapter(&RequestAdapterOptions {
-				power_preference: PowerPreference::default(),
-				compatible_surface: Some(&surface),
-				force_fallback_adapter: false,
-			})
-			.block_on()
-			.expect("Could not find adapter!");
-		let (device, queue) = adapter
-			.request_device(
-				&DeviceDescriptor {
-					label: None,
-					required_features: Features::empty(),
-					required_limits: Limits::default(),
-					memory_hints: MemoryHints::default(),
-				},
-				None,
-			)
-			.block_on()
-			.expect("Could not create device!");
-
-		let config = {
-			let scale_factor = window.scale_factor();
-			let (frame_width, frame_height) =
-				window.inner_size().pipe(|PhysicalSize { width, height }| {
-					((width as f64 * scale_factor) as u32, (height as f64 * scale_factor) as u32)
-				});
-			surface
-				.get_default_config(&adapter, frame_width, frame_height)
-				.expect("Could not get default config!")
+		// after a suspend only the surface is gone, the window and device are still usable
+		let result = match &mut self.state {
+			Some(state) => state.recreate_surface(),
+			None => self.create_state(event_loop).map(|state| {
+				let state = self.state.insert(state);
+				let mut context = window_layer_context!(self, state);
+				self.application.on_start(&mut context);
+			}),
 		};
-		surface.configure(&device, &config);
+		if let Err(error) = result {
+			core_error!("Could not initialize: {error:?}");
+			self.error.replace(error);
+			self.exit_reason = Some(ExitReason::Error);
+			event_loop.exit();
+		}
+	}
 
-		self.state.replace(State { window, surface, device, queue, config });
+	fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+		// platforms like Android invalidate the native window while suspended
+		if let Some(state) = &mut self.state {
+			state.surface = None;
+		}
 	}
 
 	fn window_event(
@@ -152,83 +711,224 @@ impl<'app, App: Application> ApplicationHandler for Context<'app, App> {
 		}
 		let state = self.state.as_mut().unwrap();
 
-		// handle winit event
-		let event = match winit_event {
-			WindowEvent::CloseRequested => Event::WindowClose,
-
-			WindowEvent::KeyboardInput { event, .. } => match event.state {
-				ElementState::Pressed => {
-					Event::KeyPressed { key: event.logical_key, is_repeat: event.repeat }
-				},
-				ElementState::Released => Event::KeyReleased { key: event.logical_key },
-			},
-
-			WindowEvent::MouseInput { state, button, .. } => match state {
-				ElementState::Pressed => Event::MouseButtonPressed(button),
-				ElementState::Released => Event::MouseButtonReleased(button),
-			},
-
-			WindowEvent::MouseWheel { delta, .. } => match delta {
-				MouseScrollDelta::LineDelta(x, y) => {
-					let line_scale = 2.0; // hard-code a line pixel size of 2
-					Event::MouseScrolled { x_offset: x * line_scale, y_offset: y * line_scale }
-				},
-				MouseScrollDelta::PixelDelta(PhysicalPosition { x, y }) => {
-					Event::MouseScrolled { x_offset: x as f32, y_offset: y as f32 }
-				},
+		// handle the window state, then translate what is left for the layers
+		match &winit_event {
+			WindowEvent::Ime(Ime::Commit(text)) => {
+				self.on_text(event_loop, text);
+				return;
 			},
 
-			WindowEvent::CursorMoved { position: PhysicalPosition { x, y }, .. } => {
-				Event::MouseMoved { x: x as f32, y: y as f32 }
+			WindowEvent::Focused(focused) => {
+				self.focused = *focused;
+				if !focused {
+					self.input.reset();
+				}
+				return;
 			},
 
 			WindowEvent::Resized(PhysicalSize { width, height }) => {
-				state.config.width = width;
-				state.config.height = height;
-				state.surface.configure(&state.device, &state.config);
-				state.window.request_redraw();
-
-				Event::WindowResize { width, height }
+				let (width, height) = (*width, *height);
+				// some platforms report minimizing as a resize to zero
+				self.minimized =
+					width == 0 || height == 0 || state.window.is_minimized().unwrap_or(false);
+				if !self.minimized {
+					state.config.width = width;
+					state.config.height = height;
+					if let Some(surface) = &state.surface {
+						surface.configure(&state.device, &state.config);
+					}
+					state.targets = RenderTargets::new(
+						&state.device,
+						&state.renderer_config,
+						Size::new(width, height),
+					);
+					state.window.request_redraw();
+				}
 			},
 
 			WindowEvent::RedrawRequested => {
-				let frame =
-					state.surface.get_current_texture().expect("Could not get next texture");
-				let view = frame.texture.create_view(&TextureViewDescriptor::default());
-				let mut encoder =
-					state.device.create_command_encoder(&CommandEncoderDescriptor { label: None });
-				{
-					let _render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-						label: None,
-						color_attachments: &[Some(RenderPassColorAttachment {
-							view: &view,
-							resolve_target: None,
-							ops: Operations {
-								load: LoadOp::Clear(Color { r: 1.0, g: 0.0, b: 1.0, a: 1.0 }),
-								store: StoreOp::Store,
-							},
-						})],
-						depth_stencil_attachment: None,
-						timestamp_writes: None,
-						occlusion_query_set: None,
-					});
-				}
-				state.queue.submit(Some(encoder.finish()));
-				frame.present();
+				self.redraw(event_loop);
 				return;
 			},
 
-			_ => {
-				return;
+			_ => {},
+		}
+
+		let typed_text = match &winit_event {
+			WindowEvent::KeyboardInput { event, .. } if event.state == ElementState::Pressed => {
+				event.text.clone()
 			},
+			_ => None,
+		};
+		let scale_factor = state.window.scale_factor();
+		let Some(event) =
+			translate::window_event(winit_event, self.spec.scroll_line_scale, scale_factor)
+		else {
+			return;
 		};
 
 		self.on_event(event_loop, &event);
+		if let Some(text) = typed_text {
+			self.on_text(event_loop, &text);
+		}
+	}
+
+	fn device_event(
+		&mut self,
+		event_loop: &ActiveEventLoop,
+		_device_id: DeviceId,
+		device_event: DeviceEvent,
+	) {
+		if self.state.is_none() || !self.focused {
+			return;
+		}
+
+		if let DeviceEvent::MouseMotion { delta: (dx, dy) } = device_event {
+			self.on_event(event_loop, &Event::MouseMotionRaw { dx: dx as f32, dy: dy as f32 });
+		}
+	}
+
+	fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+		#[cfg(feature = "gamepad")]
+		while let Some(gamepad_event) = self.gilrs.as_mut().and_then(gilrs::Gilrs::next_event) {
+			if let Some(event) = gamepad::translate(gamepad_event) {
+				self.on_event(event_loop, &event);
+			}
+		}
+
+		self.flush_mouse_move(event_loop);
+		while let Ok(event) = self.posted_events.try_recv() {
+			self.dispatch(event_loop, &event);
+		}
+
+		for event in self.replay.due_events() {
+			self.dispatch(event_loop, &event);
+		}
+
+		let continuous = self.spec.redraw_policy == RedrawPolicy::Continuous && !self.is_paused();
+		let deadline_reached = self.redraw_deadline.is_some_and(|it| it <= Instant::now());
+		if deadline_reached {
+			self.redraw_deadline = None;
+		}
+		// keep polling during playback so recorded events are dispatched on time
+		if continuous || self.replay.is_playing() {
+			event_loop.set_control_flow(ControlFlow::Poll);
+		}
+		else if let Some(deadline) =
+			self.redraw_deadline.into_iter().chain(self.gamepad_poll()).min()
+		{
+			event_loop.set_control_flow(ControlFlow::WaitUntil(deadline));
+		}
+		else {
+			event_loop.set_control_flow(ControlFlow::Wait);
+		}
+		if continuous || deadline_reached {
+			if let Some(state) = &self.state {
+				state.window.request_redraw();
+			}
+		}
+	}
+
+	fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+		if let Some(state) = self.state.as_mut() {
+			let mut context = window_layer_context!(self, state);
+			self.application.on_stop(&mut context);
+			state.screenshots.save_all(&state.device);
+		}
+		// write a recording still in progress now, rather than whenever the context is dropped
+		self.replay.stop_recording();
+	}
+}
+
+/// Sleeps for the rest of the frame budget of [`WindowSpec::frame_rate_limit`], unless
+/// `present_mode` already waits for vsync.
+fn limit_frame_rate(limit: Option<u32>, present_mode: PresentMode, frame_stats: &FrameStats) {
+	let Some(limit) = limit
+	else {
+		return;
+	};
+
+	let is_vsync = matches!(
+		present_mode,
+		PresentMode::Fifo | PresentMode::FifoRelaxed | PresentMode::AutoVsync
+	);
+	if limit > 0 && !is_vsync {
+		let budget = Duration::from_secs(1) / limit;
+		if let Some(remaining) = budget.checked_sub(frame_stats.frame_elapsed()) {
+			std::thread::sleep(remaining);
+		}
 	}
 }
 
+fn update_layers(layer_stack: &mut LayerStack, context: &mut LayerContext) {
+	crate::profile_scope!("update_layers");
+	layer_stack.update(context);
+}
+
+/// Draws every layer into `view` in a single render pass, bottom-of-stack first so overlays end
+/// up on top.
+fn render_layers(
+	layer_stack: &mut LayerStack,
+	context: &LayerContext,
+	view: &TextureView,
+	targets: &RenderTargets,
+	gpu_timer: Option<&GpuTimer>,
+) -> CommandBuffer {
+	crate::profile_scope!("render_layers");
+	let mut encoder =
+		context.device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+	// a premultiplied surface shows the color channels as they are, so a translucent clear color
+	// has to be premultiplied to come out as intended
+	let clear_color = match &context.surface {
+		Some(surface) if surface.config.alpha_mode == CompositeAlphaMode::PreMultiplied => {
+			context.render_settings.clear_color.premultiplied()
+		},
+		_ => context.render_settings.clear_color,
+	};
+	{
+		let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+			label: None,
+			// with multisampling, draw into the multisampled target and resolve into the output
+			color_attachments: &[Some(RenderPassColorAttachment {
+				view: targets.msaa.as_ref().unwrap_or(view),
+				resolve_target: targets.msaa.as_ref().map(|_| view),
+				ops: Operations {
+					load: if context.render_settings.clear {
+						LoadOp::Clear(clear_color.into())
+					}
+					else {
+						LoadOp::Load
+					},
+					store: StoreOp::Store,
+				},
+			})],
+			depth_stencil_attachment: targets.depth.as_ref().map(|view| {
+				RenderPassDepthStencilAttachment {
+					view,
+					depth_ops: Some(Operations { load: LoadOp::Clear(1.0), store: StoreOp::Store }),
+					stencil_ops: None,
+				}
+			}),
+			timestamp_writes: gpu_timer.map(GpuTimer::timestamp_writes),
+			occlusion_query_set: None,
+		});
+		layer_stack.render(context, &mut render_pass);
+	}
+	if let Some(gpu_timer) = gpu_timer {
+		gpu_timer.resolve(&mut encoder);
+	}
+	encoder.finish()
+}
+
 #[derive(Debug)]
 pub enum Error {
+	NoAdapter,
+	/// Requested device features the adapter does not support.
+	MissingFeatures(Features),
+	/// Names of requested device limits the adapter does not support.
+	UnsupportedLimits(Vec<&'static str>),
+	/// The surface ran out of memory while acquiring a frame.
+	OutOfMemory,
 	Unknown(String),
 }
 
@@ -238,14 +938,47 @@ impl From<EventLoopError> for Error {
 	}
 }
 
+impl From<RequestDeviceError> for Error {
+	fn from(value: RequestDeviceError) -> Self {
+		Self::Unknown(format!("{value}"))
+	}
+}
+
+/// Runs the layers without an [`Application`], using default callbacks for everything.
+///
+/// # Errors
+pub fn run_layers(layer_setup: impl Fn(&mut layer::LayerStack)) -> Result<ExitReason, Error> {
+	run((), layer_setup)
+}
+
 /// # Errors
 pub fn run(
 	app: impl Application,
 	layer_setup: impl Fn(&mut layer::LayerStack),
-) -> Result<(), Error> {
-	let mut context = Context::new(app, layer_setup);
+) -> Result<ExitReason, Error> {
+	run_with(app, WindowSpec::default(), layer_setup)
+}
 
-	EventLoop::new()?.run_app(&mut context)?;
+/// # Errors
+/// Fails if the event loop cannot be created or no adapter, not even the software fallback, is
+/// available.
+pub fn run_with(
+	app: impl Application,
+	spec: WindowSpec,
+	layer_setup: impl Fn(&mut layer::LayerStack),
+) -> Result<ExitReason, Error> {
+	if spec.log_panics {
+		log::install_panic_hook();
+	}
+	let mut context = Context::new(app, spec, layer_setup);
+
+	let event_loop = EventLoop::new()?;
+	event_loop.set_control_flow(ControlFlow::Poll);
+	context.event_queue.set_waker(event_loop.create_proxy());
+	event_loop.run_app(&mut context)?;
 
-	Ok(())
+	match context.error {
+		Some(error) => Err(error),
+		None => Ok(context.exit_reason.unwrap_or(ExitReason::ApplicationRequested)),
+	}
 }
@@ -1,8 +1,18 @@
 #![allow(unused)]
 
-use std::fmt::{self, Display};
+use std::{
+	collections::VecDeque,
+	fmt::{self, Display},
+	panic,
+	sync::{Arc, LazyLock, Mutex, Once, PoisonError, RwLock},
+};
 
-#[derive(Copy, Clone)]
+use chrono::{
+	format::{Item, StrftimeItems},
+	DateTime, Local, Utc,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Level {
 	Trace,
 	Debug,
@@ -25,9 +35,148 @@ impl Display for Level {
 	}
 }
 
+/// A log message as handed to [`LogSink`]s.
+#[derive(Clone, Debug)]
+pub struct Record {
+	pub timestamp: DateTime<Local>,
+	pub level: Level,
+	/// `"HAZEL"` for engine messages, `"APP"` for application messages.
+	pub source: &'static str,
+	pub message: String,
+}
+
+/// Receives every log message in addition to stdout. Sinks may be called from any thread.
+pub trait LogSink: Send + Sync {
+	fn log(&self, record: &Record);
+}
+
+static SINKS: RwLock<Vec<Arc<dyn LogSink>>> = RwLock::new(Vec::new());
+
+pub fn add_sink(sink: Arc<dyn LogSink>) {
+	SINKS.write().unwrap_or_else(PoisonError::into_inner).push(sink);
+}
+
+pub fn remove_sink(sink: &Arc<dyn LogSink>) {
+	SINKS.write().unwrap_or_else(PoisonError::into_inner).retain(|it| !Arc::ptr_eq(it, sink));
+}
+
+/// How timestamps are printed to stdout. Sinks receive the timestamp itself.
+struct TimestampFormat {
+	/// Parsed once when set, `None` to print no timestamp.
+	items: Option<Vec<Item<'static>>>,
+	utc: bool,
+}
+
+static TIMESTAMP_FORMAT: LazyLock<RwLock<TimestampFormat>> = LazyLock::new(|| {
+	let items = StrftimeItems::new("%F %T%.3f").parse_to_owned().ok();
+	RwLock::new(TimestampFormat { items, utc: false })
+});
+
+/// Sets the `strftime`-style format of the timestamps printed to stdout, `%F %T%.3f` by default.
+/// `None` or an empty format prints no timestamp. Returns `false` and keeps the current format if
+/// `format` is invalid.
+pub fn set_timestamp_format(format: Option<&str>) -> bool {
+	let items = match format.filter(|it| !it.is_empty()) {
+		Some(format) => match StrftimeItems::new(format).parse_to_owned() {
+			Ok(items) => Some(items),
+			Err(_) => return false,
+		},
+		None => None,
+	};
+	TIMESTAMP_FORMAT.write().unwrap_or_else(PoisonError::into_inner).items = items;
+	true
+}
+
+/// Prints timestamps in UTC instead of local time.
+pub fn set_timestamp_utc(utc: bool) {
+	TIMESTAMP_FORMAT.write().unwrap_or_else(PoisonError::into_inner).utc = utc;
+}
+
+fn dispatch(level: Level, source: &'static str, message: impl Display) {
+	let timestamp = Local::now();
+	{
+		let format = TIMESTAMP_FORMAT.read().unwrap_or_else(PoisonError::into_inner);
+		match &format.items {
+			Some(items) if format.utc => {
+				let timestamp = timestamp.with_timezone(&Utc).format_with_items(items.iter());
+				println!("{timestamp} {level:<5} [{source}] {message}");
+			},
+			Some(items) => {
+				let timestamp = timestamp.format_with_items(items.iter());
+				println!("{timestamp} {level:<5} [{source}] {message}");
+			},
+			None => println!("{level:<5} [{source}] {message}"),
+		}
+	}
+
+	let sinks = SINKS.read().unwrap_or_else(PoisonError::into_inner);
+	if !sinks.is_empty() {
+		let record = Record { timestamp, level, source, message: message.to_string() };
+		for sink in sinks.iter() {
+			sink.log(&record);
+		}
+	}
+}
+
+/// A [`LogSink`] keeping the most recent `capacity` records, e.g. for an in-engine console.
+pub struct LogCapture {
+	capacity: usize,
+	records: Mutex<VecDeque<Record>>,
+}
+
+impl LogCapture {
+	#[must_use]
+	pub fn new(capacity: usize) -> Self {
+		LogCapture { capacity, records: Mutex::new(VecDeque::with_capacity(capacity)) }
+	}
+
+	/// A snapshot of the captured records at or above `min_level`, oldest first.
+	#[must_use]
+	pub fn records(&self, min_level: Level) -> Vec<Record> {
+		let records = self.records.lock().unwrap_or_else(PoisonError::into_inner);
+		records.iter().filter(|it| it.level >= min_level).cloned().collect()
+	}
+
+	pub fn clear(&self) {
+		self.records.lock().unwrap_or_else(PoisonError::into_inner).clear();
+	}
+}
+
+impl LogSink for LogCapture {
+	fn log(&self, record: &Record) {
+		if self.capacity == 0 {
+			return;
+		}
+		let mut records = self.records.lock().unwrap_or_else(PoisonError::into_inner);
+		if records.len() == self.capacity {
+			records.pop_front();
+		}
+		records.push_back(record.clone());
+	}
+}
+
 pub(crate) fn core_log(level: Level, message: impl Display) {
-	let timestamp = chrono::Local::now().format("%F %T%.3f");
-	println!("{timestamp} {level:<5} [HAZEL] {message}");
+	dispatch(level, "HAZEL", message);
+}
+
+/// Logs panics through the engine logger and its sinks, in release builds too, before handing
+/// them to the previously installed hook. Installing more than once has no further effect.
+pub(crate) fn install_panic_hook() {
+	static INSTALL: Once = Once::new();
+	INSTALL.call_once(|| {
+		let previous = panic::take_hook();
+		panic::set_hook(Box::new(move |info| {
+			let payload = info.payload();
+			let message = payload
+				.downcast_ref::<&str>()
+				.copied()
+				.or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+				.unwrap_or("Box<dyn Any>");
+			let location = info.location().map(|it| format!(" at {it}")).unwrap_or_default();
+			core_log(Level::Error, format!("Panicked{location}: {message}"));
+			previous(info);
+		}));
+	});
 }
 
 macro_rules! core_trace {
@@ -156,8 +305,7 @@ macro_rules! core_error {
 pub(crate) use core_error;
 
 pub fn log(level: Level, message: impl Display) {
-	let timestamp = chrono::Local::now().format("%F %T%.3f");
-	println!("{timestamp} {level:<5} [APP] {message}");
+	dispatch(level, "APP", message);
 }
 
 #[macro_export]
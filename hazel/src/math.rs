@@ -0,0 +1,7 @@
+//! Vector and matrix types used throughout the engine.
+//!
+//! These are re-exports of [`glam`] rather than wrappers, so they work directly with any crate
+//! built on `glam`. Engine APIs take and return these types, and [`Size`](crate::geometry::Size)
+//! and [`Position`](crate::geometry::Position) convert to and from [`Vec2`].
+
+pub use glam::{Mat3, Mat4, Quat, Vec2, Vec3, Vec4};
@@ -0,0 +1,57 @@
+use winit::monitor::{MonitorHandle, VideoModeHandle};
+
+use crate::geometry::{Position, Size};
+
+/// A snapshot of a connected monitor, as returned by
+/// [`crate::context::LayerContext::available_monitors`]. Monitors can be connected, removed or
+/// rearranged at any time, so query them again rather than keeping these around.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonitorInfo {
+	/// A human-readable name, if the platform provides one.
+	pub name: Option<String>,
+	/// The top-left corner on the virtual desktop, in physical pixels.
+	pub position: Position<i32>,
+	/// The resolution in physical pixels.
+	pub size: Size<u32>,
+	/// The refresh rate of the current video mode in millihertz, e.g. `59_940`.
+	pub refresh_rate_millihertz: Option<u32>,
+	pub scale_factor: f64,
+	/// Whether this is the primary monitor. Always `false` on platforms without the concept,
+	/// such as Wayland.
+	pub is_primary: bool,
+	handle: MonitorHandle,
+}
+
+impl MonitorInfo {
+	pub(crate) fn new(handle: MonitorHandle, primary: Option<&MonitorHandle>) -> Self {
+		let position = handle.position();
+		let size = handle.size();
+		MonitorInfo {
+			name: handle.name(),
+			position: Position::new(position.x, position.y),
+			size: Size::new(size.width, size.height),
+			refresh_rate_millihertz: handle.refresh_rate_millihertz(),
+			scale_factor: handle.scale_factor(),
+			is_primary: primary == Some(&handle),
+			handle,
+		}
+	}
+
+	/// The refresh rate in hertz, e.g. `59.94`.
+	#[must_use]
+	pub fn refresh_rate(&self) -> Option<f32> {
+		self.refresh_rate_millihertz.map(|it| (f64::from(it) / 1000.0) as f32)
+	}
+
+	/// The video modes for exclusive fullscreen, with
+	/// [`Fullscreen::Exclusive`](crate::Fullscreen::Exclusive).
+	pub fn video_modes(&self) -> impl Iterator<Item = VideoModeHandle> {
+		self.handle.video_modes()
+	}
+
+	/// The winit monitor, e.g. for [`Fullscreen::Borderless`](crate::Fullscreen::Borderless).
+	#[must_use]
+	pub fn handle(&self) -> &MonitorHandle {
+		&self.handle
+	}
+}
@@ -0,0 +1,159 @@
+//! Sharing input events between two instances over TCP.
+
+use std::{
+	io::{self, BufReader, BufWriter, Read, Write},
+	net::{TcpListener, TcpStream, ToSocketAddrs},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		mpsc::{self, Receiver, Sender},
+		Arc,
+	},
+	thread,
+};
+
+use winit::event_loop::ActiveEventLoop;
+
+use crate::{
+	context::LayerContext,
+	core_info, core_warn,
+	event::{Event, EventCategory},
+	layer::{EventResult, Layer},
+};
+
+/// Frames larger than this are treated as a broken connection.
+const MAX_FRAME_SIZE: u32 = 1 << 20;
+
+/// A layer that sends local input events to a peer and passes the peer's events to
+/// [`crate::Application::on_remote_event`], or to [`crate::Harness::remote_events`] in
+/// headless runs.
+///
+/// Events are sent as JSON, each prefixed with its length as a big-endian `u32`. Local events are
+/// only sent while connected, and are not consumed, so layers below still receive them. Remote
+/// events do not reach the layers or the input state.
+pub struct NetLayer {
+	connected: Arc<AtomicBool>,
+	outgoing: Sender<Event>,
+	incoming: Receiver<Event>,
+}
+
+impl NetLayer {
+	/// Listens on `address` and accepts a single peer in the background.
+	///
+	/// # Errors
+	/// Fails if the address cannot be bound.
+	pub fn host(address: impl ToSocketAddrs) -> io::Result<Self> {
+		let listener = TcpListener::bind(address)?;
+		Ok(NetLayer::spawn(move || {
+			let (stream, peer) = listener.accept()?;
+			core_info!("Peer {peer} connected");
+			Ok(stream)
+		}))
+	}
+
+	/// Connects to a peer that is hosting on `address`.
+	///
+	/// # Errors
+	/// Fails if the connection cannot be established.
+	pub fn connect(address: impl ToSocketAddrs) -> io::Result<Self> {
+		let stream = TcpStream::connect(address)?;
+		Ok(NetLayer::spawn(move || Ok(stream)))
+	}
+
+	#[must_use]
+	pub fn is_connected(&self) -> bool {
+		self.connected.load(Ordering::Relaxed)
+	}
+
+	fn spawn(open: impl FnOnce() -> io::Result<TcpStream> + Send + 'static) -> Self {
+		let connected = Arc::new(AtomicBool::new(false));
+		let (outgoing, outgoing_receiver) = mpsc::channel();
+		let (incoming_sender, incoming) = mpsc::channel();
+
+		let is_connected = connected.clone();
+		thread::spawn(move || {
+			let result = open().and_then(|stream| {
+				stream.set_nodelay(true)?;
+				let writer = stream.try_clone()?;
+				thread::spawn(move || write_events(writer, &outgoing_receiver));
+
+				is_connected.store(true, Ordering::Relaxed);
+				read_events(stream, &incoming_sender)
+			});
+			is_connected.store(false, Ordering::Relaxed);
+			match result {
+				Ok(()) => core_info!("Peer disconnected"),
+				Err(error) => core_warn!("Peer connection lost: {error}"),
+			}
+		});
+
+		NetLayer { connected, outgoing, incoming }
+	}
+}
+
+impl Layer for NetLayer {
+	fn name(&self) -> &'static str {
+		"NetLayer"
+	}
+
+	fn on_update(&mut self, context: &mut LayerContext) {
+		context.remote_events.extend(self.incoming.try_iter());
+	}
+
+	fn on_event(&mut self, _event_loop: &ActiveEventLoop, event: &Event) -> EventResult {
+		if self.is_connected() {
+			self.outgoing.send(event.clone()).ok();
+			return EventResult::Observe;
+		}
+		EventResult::Pass
+	}
+
+	fn event_mask(&self) -> EventCategory {
+		EventCategory::INPUT
+	}
+}
+
+/// Reads frames until the peer closes the connection.
+fn read_events(stream: TcpStream, incoming: &Sender<Event>) -> io::Result<()> {
+	let mut reader = BufReader::new(stream);
+	loop {
+		let mut length = [0; 4];
+		match reader.read_exact(&mut length) {
+			Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+			result => result?,
+		}
+		let length = u32::from_be_bytes(length);
+		if length > MAX_FRAME_SIZE {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("Frame of {length} bytes exceeds the maximum size"),
+			));
+		}
+
+		let mut frame = vec![0; length as usize];
+		reader.read_exact(&mut frame)?;
+		match serde_json::from_slice(&frame) {
+			Ok(event) => {
+				if incoming.send(event).is_err() {
+					// the layer was dropped
+					return Ok(());
+				}
+			},
+			Err(error) => core_warn!("Skipping malformed event from peer: {error}"),
+		}
+	}
+}
+
+fn write_events(stream: TcpStream, outgoing: &Receiver<Event>) {
+	let mut writer = BufWriter::new(stream);
+	for event in outgoing {
+		let result = serde_json::to_vec(&event).map_err(io::Error::from).and_then(|frame| {
+			writer.write_all(&(frame.len() as u32).to_be_bytes())?;
+			writer.write_all(&frame)?;
+			writer.flush()
+		});
+		if result.is_err() {
+			// the reader notices the broken connection and reports it
+			return;
+		}
+	}
+}
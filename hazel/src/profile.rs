@@ -0,0 +1,141 @@
+//! Scope timing written as a Chrome trace, to open in `chrome://tracing` or Perfetto.
+//!
+//! Wrap the code of interest in [`profile_scope!`](crate::profile_scope) and record a session
+//! with [`begin_session`] and [`end_session`]. Scopes outside a session are not recorded.
+
+use std::{
+	fmt::Write as _,
+	fs, io,
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Mutex, PoisonError,
+	},
+	time::Instant,
+};
+
+use crate::core_warn;
+
+struct Session {
+	name: String,
+	path: PathBuf,
+	start: Instant,
+	events: Vec<TraceEvent>,
+}
+
+struct TraceEvent {
+	name: &'static str,
+	thread_id: u64,
+	start: Instant,
+	end: Instant,
+}
+
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+thread_local! {
+	static THREAD_ID: u64 = {
+		static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(0);
+		NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed)
+	};
+}
+
+/// Starts recording scopes, to be written to `path` by [`end_session`]. A session that is
+/// already running is ended first.
+pub fn begin_session(name: &str, path: impl AsRef<Path>) {
+	if SESSION.lock().unwrap_or_else(PoisonError::into_inner).is_some() {
+		core_warn!("Profile session {name:?} started while another session is running");
+		if let Err(error) = end_session() {
+			core_warn!("Could not write profile: {error}");
+		}
+	}
+
+	*SESSION.lock().unwrap_or_else(PoisonError::into_inner) = Some(Session {
+		name: name.to_string(),
+		path: path.as_ref().to_path_buf(),
+		start: Instant::now(),
+		events: Vec::new(),
+	});
+}
+
+/// Stops recording and writes the trace. Does nothing if no session is running.
+///
+/// # Errors
+/// Fails if the trace file cannot be written.
+pub fn end_session() -> io::Result<()> {
+	let Some(session) = SESSION.lock().unwrap_or_else(PoisonError::into_inner).take()
+	else {
+		return Ok(());
+	};
+
+	let mut json = String::from("{\"otherData\":{\"name\":");
+	write_json_string(&mut json, &session.name);
+	json.push_str("},\"traceEvents\":[");
+	for (index, event) in session.events.iter().enumerate() {
+		if index > 0 {
+			json.push(',');
+		}
+		json.push_str("{\"cat\":\"function\",\"ph\":\"X\",\"pid\":0,\"name\":");
+		write_json_string(&mut json, event.name);
+		// timestamps are in microseconds
+		let start = event.start.duration_since(session.start).as_secs_f64() * 1_000_000.0;
+		let duration = event.end.duration_since(event.start).as_secs_f64() * 1_000_000.0;
+		let _ =
+			write!(json, ",\"tid\":{},\"ts\":{start:.3},\"dur\":{duration:.3}}}", event.thread_id);
+	}
+	json.push_str("]}");
+
+	fs::write(&session.path, json)
+}
+
+fn write_json_string(json: &mut String, value: &str) {
+	json.push('"');
+	for character in value.chars() {
+		match character {
+			'"' => json.push_str("\\\""),
+			'\\' => json.push_str("\\\\"),
+			character if character.is_control() => {
+				let _ = write!(json, "\\u{:04x}", character as u32);
+			},
+			character => json.push(character),
+		}
+	}
+	json.push('"');
+}
+
+/// Records the time from its creation until it is dropped. Created by
+/// [`profile_scope!`](crate::profile_scope).
+pub struct ProfileTimer {
+	name: &'static str,
+	start: Instant,
+}
+
+impl ProfileTimer {
+	#[must_use]
+	pub fn new(name: &'static str) -> Self {
+		ProfileTimer { name, start: Instant::now() }
+	}
+}
+
+impl Drop for ProfileTimer {
+	fn drop(&mut self) {
+		let end = Instant::now();
+		if let Some(session) = SESSION.lock().unwrap_or_else(PoisonError::into_inner).as_mut() {
+			session.events.push(TraceEvent {
+				name: self.name,
+				thread_id: THREAD_ID.with(|it| *it),
+				start: self.start,
+				end,
+			});
+		}
+	}
+}
+
+/// Times the rest of the enclosing scope under the given name. Only debug builds with the
+/// `profile` feature record anything.
+#[macro_export]
+macro_rules! profile_scope {
+	($name:expr) => {
+		let _profile_timer =
+			cfg!(debug_assertions).then(|| $crate::profile::ProfileTimer::new($name));
+	};
+}
@@ -0,0 +1,227 @@
+//! Recording and playback of input events, to reproduce a session.
+
+#[cfg(feature = "serde")]
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "serde")]
+use crate::core_info;
+use crate::{
+	core_error,
+	event::{Event, EventCategory},
+};
+
+/// Recordings with another version were made by an incompatible engine and are not played.
+pub const RECORDING_VERSION: u32 = 2;
+
+/// An event and when it occurred, relative to the start of the recording.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordedEvent {
+	pub elapsed: Duration,
+	pub event: Event,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Recording {
+	pub version: u32,
+	pub events: Vec<RecordedEvent>,
+}
+
+#[cfg(feature = "serde")]
+impl Recording {
+	/// Writes the recording to `path` as JSON.
+	///
+	/// # Errors
+	/// Fails if the file cannot be written.
+	pub fn save(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+		let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+		serde_json::to_writer(file, self).map_err(std::io::Error::from)
+	}
+
+	/// Reads a recording written by [`Recording::save`]. Recordings of another version load, but
+	/// are refused when played.
+	///
+	/// # Errors
+	/// Fails if the file cannot be read or is not a recording.
+	pub fn load(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+		let file = std::io::BufReader::new(std::fs::File::open(path)?);
+		serde_json::from_reader(file).map_err(std::io::Error::from)
+	}
+}
+
+impl Default for Recording {
+	fn default() -> Self {
+		Recording { version: RECORDING_VERSION, events: Vec::new() }
+	}
+}
+
+/// Appends every dispatched event to a recording.
+pub(crate) struct InputRecorder {
+	start: Instant,
+	recording: Recording,
+	/// Where the recording is written when it stops.
+	#[cfg(feature = "serde")]
+	path: Option<PathBuf>,
+}
+
+impl InputRecorder {
+	fn new() -> Self {
+		InputRecorder {
+			start: Instant::now(),
+			recording: Recording::default(),
+			#[cfg(feature = "serde")]
+			path: None,
+		}
+	}
+
+	/// Ends the recording, writing it to its path if it has one.
+	fn finish(self) -> Recording {
+		#[cfg(feature = "serde")]
+		if let Some(path) = &self.path {
+			let display = path.display();
+			match self.recording.save(path) {
+				Ok(()) => core_info!("Saved recording to {display}"),
+				Err(error) => core_error!("Could not save recording to {display}: {error}"),
+			}
+		}
+		self.recording
+	}
+
+	fn record(&mut self, event: &Event) {
+		let elapsed = self.start.elapsed();
+		self.recording.events.push(RecordedEvent { elapsed, event: event.clone() });
+	}
+}
+
+/// Replays a recording in real time. While it plays, live input events are ignored.
+pub(crate) struct InputPlayer {
+	start: Instant,
+	recording: Recording,
+	next: usize,
+}
+
+impl InputPlayer {
+	fn new(recording: Recording) -> Self {
+		InputPlayer { start: Instant::now(), recording, next: 0 }
+	}
+
+	fn is_finished(&self) -> bool {
+		self.next >= self.recording.events.len()
+	}
+}
+
+/// The recorder and player of a context.
+#[derive(Default)]
+pub(crate) struct Replay {
+	recorder: Option<InputRecorder>,
+	player: Option<InputPlayer>,
+}
+
+impl Replay {
+	pub(crate) fn is_recording(&self) -> bool {
+		self.recorder.is_some()
+	}
+
+	pub(crate) fn is_playing(&self) -> bool {
+		self.player.is_some()
+	}
+
+	pub(crate) fn start_recording(&mut self) {
+		self.recorder = Some(InputRecorder::new());
+	}
+
+	/// Starts recording, to be written to `path` when the recording stops, at the latest on exit.
+	#[cfg(feature = "serde")]
+	pub(crate) fn start_recording_to(&mut self, path: PathBuf) {
+		self.recorder = Some(InputRecorder { path: Some(path), ..InputRecorder::new() });
+	}
+
+	pub(crate) fn stop_recording(&mut self) -> Option<Recording> {
+		self.recorder.take().map(InputRecorder::finish)
+	}
+
+	/// Starts playing `recording`, replacing any recording that is playing. Recordings of another
+	/// version are refused.
+	pub(crate) fn play(&mut self, recording: Recording) -> bool {
+		if recording.version != RECORDING_VERSION {
+			let version = recording.version;
+			core_error!("Cannot play recording of version {version}, expected {RECORDING_VERSION}");
+			return false;
+		}
+		self.player = Some(InputPlayer::new(recording));
+		true
+	}
+
+	pub(crate) fn stop_playing(&mut self) {
+		self.player = None;
+	}
+
+	/// Whether a live event should be dispatched, i.e. it is not input overridden by playback.
+	pub(crate) fn accepts_live(&self, event: &Event) -> bool {
+		!(self.is_playing() && event.is_in_category(EventCategory::INPUT))
+	}
+
+	pub(crate) fn record(&mut self, event: &Event) {
+		// user events cannot be serialized
+		if event.is_in_category(EventCategory::USER) {
+			return;
+		}
+		if let Some(recorder) = &mut self.recorder {
+			recorder.record(event);
+		}
+	}
+
+	/// Takes the recorded events that are due, ending playback after the last one.
+	pub(crate) fn due_events(&mut self) -> Vec<Event> {
+		let Some(player) = &mut self.player
+		else {
+			return Vec::new();
+		};
+
+		let elapsed = player.start.elapsed();
+		let due = player.recording.events[player.next..]
+			.iter()
+			.take_while(|it| it.elapsed <= elapsed)
+			.map(|it| it.event.clone())
+			.collect::<Vec<_>>();
+		player.next += due.len();
+
+		if player.is_finished() {
+			self.player = None;
+		}
+		due
+	}
+}
+
+/// Writes a recording still in progress, so it is not lost when the application exits.
+impl Drop for Replay {
+	fn drop(&mut self) {
+		self.stop_recording();
+	}
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+	use std::{fs, process};
+
+	use super::{Recording, Replay};
+	use crate::event::Event;
+
+	#[test]
+	fn recordings_with_a_path_are_written_when_dropped() {
+		let path = std::env::temp_dir().join(format!("hazel-recording-{}.json", process::id()));
+		let mut replay = Replay::default();
+		replay.start_recording_to(path.clone());
+		replay.record(&Event::KeyTyped('a'));
+		replay.record(&Event::WindowClose);
+		assert!(!path.exists());
+
+		drop(replay);
+		let recording = Recording::load(&path);
+		fs::remove_file(&path).unwrap();
+		let events: Vec<_> = recording.unwrap().events.into_iter().map(|it| it.event).collect();
+		assert_eq!(events, [Event::KeyTyped('a'), Event::WindowClose]);
+	}
+}
@@ -0,0 +1,196 @@
+use wgpu::{
+	CommandEncoder, CompareFunction, DepthBiasState, DepthStencilState, Device, Extent3d,
+	ImageCopyTexture, ImageDataLayout, LoadOp, MultisampleState, Operations, Queue, RenderPass,
+	RenderPassColorAttachment, RenderPassDescriptor, StencilState, StoreOp, Texture,
+	TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView,
+	TextureViewDescriptor,
+};
+
+use crate::{geometry::Size, gpu::DEPTH_FORMAT, Color};
+
+/// The formats and sample count of the shared render pass, which every pipeline drawing in it
+/// must match. Layers read them from [`crate::context::LayerContext::renderer_config`] instead
+/// of assuming their own.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RendererConfig {
+	/// The color format. This is an sRGB format unless [`crate::WindowSpec::surface_format`]
+	/// forces another one, so shaders should output linear colors like [`Color`] holds and leave
+	/// the encoding to the target.
+	pub format: TextureFormat,
+	/// The number of samples per pixel, which may be lower than requested in
+	/// [`crate::WindowSpec::sample_count`] if the adapter does not support it.
+	pub sample_count: u32,
+	/// The depth test, `None` without a depth buffer. See [`crate::WindowSpec::depth_compare`].
+	pub depth_compare: Option<CompareFunction>,
+}
+
+impl RendererConfig {
+	/// The format of the depth buffer, or `None` without one.
+	#[must_use]
+	pub fn depth_format(&self) -> Option<TextureFormat> {
+		self.depth_compare.map(|_| DEPTH_FORMAT)
+	}
+
+	/// The depth state for pipelines. Pipelines must use it as is, since a pass with a depth
+	/// attachment only accepts pipelines with a matching one.
+	#[must_use]
+	pub fn depth_stencil_state(&self) -> Option<DepthStencilState> {
+		self.depth_compare.map(|depth_compare| DepthStencilState {
+			format: DEPTH_FORMAT,
+			depth_write_enabled: true,
+			depth_compare,
+			stencil: StencilState::default(),
+			bias: DepthBiasState::default(),
+		})
+	}
+
+	#[must_use]
+	pub fn multisample_state(&self) -> MultisampleState {
+		MultisampleState { count: self.sample_count, ..MultisampleState::default() }
+	}
+}
+
+/// A 2D RGBA texture for sampling in shaders.
+pub struct Texture2D {
+	size: Size<u32>,
+	texture: Texture,
+	view: TextureView,
+}
+
+impl Texture2D {
+	/// Creates a texture from tightly packed sRGB RGBA8 pixels.
+	///
+	/// # Panics
+	/// Panics if `pixels` does not hold exactly `size.width * size.height` pixels.
+	#[must_use]
+	pub fn from_rgba8(device: &Device, queue: &Queue, size: Size<u32>, pixels: &[u8]) -> Self {
+		assert_eq!(
+			pixels.len(),
+			size.width as usize * size.height as usize * 4,
+			"Pixel data does not match the texture size"
+		);
+
+		let extent = Extent3d { width: size.width, height: size.height, depth_or_array_layers: 1 };
+		let texture = device.create_texture(&TextureDescriptor {
+			label: Some("texture"),
+			size: extent,
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: TextureDimension::D2,
+			format: TextureFormat::Rgba8UnormSrgb,
+			usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+			view_formats: &[],
+		});
+		queue.write_texture(
+			ImageCopyTexture::from(texture.as_image_copy()),
+			pixels,
+			ImageDataLayout {
+				offset: 0,
+				bytes_per_row: Some(size.width * 4),
+				rows_per_image: Some(size.height),
+			},
+			extent,
+		);
+		let view = texture.create_view(&TextureViewDescriptor::default());
+		Texture2D { size, texture, view }
+	}
+
+	#[must_use]
+	pub fn size(&self) -> Size<u32> {
+		self.size
+	}
+
+	#[must_use]
+	pub fn texture(&self) -> &Texture {
+		&self.texture
+	}
+
+	#[must_use]
+	pub fn view(&self) -> &TextureView {
+		&self.view
+	}
+}
+
+/// An offscreen color target to render into, e.g. the scene shown in an editor viewport.
+///
+/// The color texture can be sampled in later passes. Pipelines drawing into a framebuffer must
+/// use its [`format`](Framebuffer::format), a single sample and no depth state.
+pub struct Framebuffer {
+	format: TextureFormat,
+	size: Size<u32>,
+	texture: Texture,
+	view: TextureView,
+}
+
+impl Framebuffer {
+	/// Creates a framebuffer of the given size. Empty sizes are clamped to 1x1, since wgpu
+	/// cannot create empty textures.
+	#[must_use]
+	pub fn new(device: &Device, format: TextureFormat, size: Size<u32>) -> Self {
+		let size = Size::new(size.width.max(1), size.height.max(1));
+		let texture = device.create_texture(&TextureDescriptor {
+			label: Some("framebuffer"),
+			size: Extent3d { width: size.width, height: size.height, depth_or_array_layers: 1 },
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: TextureDimension::D2,
+			format,
+			usage: TextureUsages::RENDER_ATTACHMENT
+				| TextureUsages::TEXTURE_BINDING
+				| TextureUsages::COPY_SRC,
+			view_formats: &[],
+		});
+		let view = texture.create_view(&TextureViewDescriptor::default());
+		Framebuffer { format, size, texture, view }
+	}
+
+	/// Recreates the color texture if `size` differs from the current size. Views and bind
+	/// groups of the previous texture keep pointing at the old one and must be recreated.
+	pub fn resize(&mut self, device: &Device, size: Size<u32>) {
+		if size != self.size {
+			*self = Framebuffer::new(device, self.format, size);
+		}
+	}
+
+	/// Begins a render pass into the framebuffer, cleared to `clear_color`. The framebuffer stays
+	/// bound until the returned pass is dropped.
+	pub fn bind<'encoder>(
+		&self,
+		encoder: &'encoder mut CommandEncoder,
+		clear_color: Color,
+	) -> RenderPass<'encoder> {
+		encoder.begin_render_pass(&RenderPassDescriptor {
+			label: Some("framebuffer pass"),
+			color_attachments: &[Some(RenderPassColorAttachment {
+				view: &self.view,
+				resolve_target: None,
+				ops: Operations { load: LoadOp::Clear(clear_color.into()), store: StoreOp::Store },
+			})],
+			depth_stencil_attachment: None,
+			timestamp_writes: None,
+			occlusion_query_set: None,
+		})
+	}
+
+	#[must_use]
+	pub fn format(&self) -> TextureFormat {
+		self.format
+	}
+
+	#[must_use]
+	pub fn size(&self) -> Size<u32> {
+		self.size
+	}
+
+	/// The color texture, e.g. to copy it to a buffer.
+	#[must_use]
+	pub fn texture(&self) -> &Texture {
+		&self.texture
+	}
+
+	/// A view of the color texture, to bind it for sampling in another pass.
+	#[must_use]
+	pub fn view(&self) -> &TextureView {
+		&self.view
+	}
+}
@@ -0,0 +1,226 @@
+//! Saving rendered frames as PNG files.
+
+use std::{
+	path::{Path, PathBuf},
+	thread::{self, JoinHandle},
+};
+
+use chrono::Local;
+use image::RgbaImage;
+use wgpu::{Device, Extent3d, Queue, Texture, TextureFormat};
+
+use crate::{
+	context::LayerContext, core_error, core_info, core_warn, gpu::TextureReadback, layer::Layer,
+	Error, KeyCode,
+};
+
+/// A layer that saves a screenshot of the window when a key is pressed, [`KeyCode::F12`] by
+/// default. Files are named after the time they were taken, to the millisecond, e.g.
+/// `screenshot-20240131-142501-042.png`, with a counter appended for several in the same
+/// millisecond. They are written to the working directory unless another directory is set.
+pub struct ScreenshotLayer {
+	key: KeyCode,
+	directory: PathBuf,
+	/// The timestamp of the last screenshot, and how many more were taken within its millisecond.
+	last_timestamp: String,
+	repeats: u32,
+}
+
+impl ScreenshotLayer {
+	#[must_use]
+	pub fn new() -> Self {
+		ScreenshotLayer {
+			key: KeyCode::F12,
+			directory: PathBuf::new(),
+			last_timestamp: String::new(),
+			repeats: 0,
+		}
+	}
+
+	#[must_use]
+	pub fn with_key(self, key: KeyCode) -> Self {
+		ScreenshotLayer { key, ..self }
+	}
+
+	#[must_use]
+	pub fn with_directory(self, directory: impl Into<PathBuf>) -> Self {
+		ScreenshotLayer { directory: directory.into(), ..self }
+	}
+}
+
+impl Default for ScreenshotLayer {
+	fn default() -> Self {
+		ScreenshotLayer::new()
+	}
+}
+
+impl Layer for ScreenshotLayer {
+	fn name(&self) -> &'static str {
+		"Screenshot"
+	}
+
+	fn on_update(&mut self, context: &mut LayerContext) {
+		if context.input().is_key_just_pressed(self.key) {
+			let timestamp = Local::now().format("%Y%m%d-%H%M%S-%3f").to_string();
+			if timestamp == self.last_timestamp {
+				self.repeats += 1;
+			}
+			else {
+				self.last_timestamp = timestamp;
+				self.repeats = 0;
+			}
+			let file_name = match self.repeats {
+				0 => format!("screenshot-{}.png", self.last_timestamp),
+				repeats => format!("screenshot-{}-{repeats}.png", self.last_timestamp),
+			};
+			context.capture_screenshot(self.directory.join(file_name));
+		}
+	}
+}
+
+/// A screenshot being read back from the GPU, written once the readback finishes on a later
+/// frame.
+struct PendingScreenshot {
+	readback: TextureReadback,
+	is_bgra: bool,
+	path: PathBuf,
+}
+
+/// The screenshots of a context that are not written yet: those still being read back from the
+/// GPU, and those being written on background threads.
+#[derive(Default)]
+pub(crate) struct Screenshots {
+	pending: Vec<PendingScreenshot>,
+	writers: Vec<JoinHandle<()>>,
+}
+
+impl Screenshots {
+	/// Whether a screenshot is still being read back, so frames should keep coming.
+	pub(crate) fn is_reading_back(&self) -> bool {
+		!self.pending.is_empty()
+	}
+
+	/// Starts reading back `texture` to save it to `path` as a PNG. Skips textures other than
+	/// 8-bit RGBA and BGRA with a warning.
+	pub(crate) fn capture(
+		&mut self,
+		device: &Device,
+		queue: &Queue,
+		texture: &Texture,
+		path: PathBuf,
+	) {
+		let format = texture.format();
+		let is_bgra = match format {
+			TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+			TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+			_ => {
+				core_warn!("Cannot take a screenshot of a {format:?} texture");
+				return;
+			},
+		};
+		let readback = TextureReadback::new(device, queue, texture);
+		self.pending.push(PendingScreenshot { readback, is_bgra, path });
+	}
+
+	/// Writes the screenshots whose readback finished on background threads, keeping the others.
+	pub(crate) fn save_finished(&mut self, device: &Device) {
+		self.writers.retain(|writer| !writer.is_finished());
+		let writers = &mut self.writers;
+		self.pending.retain(|screenshot| {
+			let Some(pixels) = screenshot.readback.try_take(device)
+			else {
+				return true;
+			};
+			let (is_bgra, path) = (screenshot.is_bgra, screenshot.path.clone());
+			let size = screenshot.readback.size();
+			writers.push(thread::spawn(move || write(pixels, is_bgra, size, &path)));
+			false
+		});
+	}
+
+	/// Writes all screenshots before returning, e.g. before exiting: waits for the pending ones
+	/// and writes them on this thread, and joins the background writers.
+	pub(crate) fn save_all(&mut self, device: &Device) {
+		for PendingScreenshot { readback, is_bgra, path } in self.pending.drain(..) {
+			let size = readback.size();
+			write(readback.wait(device), is_bgra, size, &path);
+		}
+		for writer in self.writers.drain(..) {
+			if writer.join().is_err() {
+				core_error!("A screenshot writer panicked");
+			}
+		}
+	}
+}
+
+fn write(pixels: Result<Vec<u8>, Error>, is_bgra: bool, size: Extent3d, path: &Path) {
+	let mut pixels = match pixels {
+		Ok(pixels) => pixels,
+		Err(error) => {
+			core_error!("Could not read back the screenshot: {error:?}");
+			return;
+		},
+	};
+	if is_bgra {
+		for pixel in pixels.chunks_exact_mut(4) {
+			pixel.swap(0, 2);
+		}
+	}
+
+	let Some(image) = RgbaImage::from_raw(size.width, size.height, pixels)
+	else {
+		return;
+	};
+	let display = path.display();
+	match image.save(path) {
+		Ok(()) => core_info!("Saved screenshot to {display}"),
+		Err(error) => core_error!("Could not save screenshot to {display}: {error}"),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{fs, path::Path, process, time::Duration};
+
+	use super::ScreenshotLayer;
+	use crate::{event::Event, geometry::Size, Error, Harness, KeyCode};
+
+	#[test]
+	fn screenshots_are_written_by_the_end_of_the_run() {
+		let directory = std::env::temp_dir().join(format!("hazel-screenshots-{}", process::id()));
+		fs::create_dir_all(&directory).unwrap();
+		let harness = Harness::new(
+			(),
+			|layer_stack| {
+				layer_stack.push_overlay(ScreenshotLayer::new().with_directory(&directory));
+			},
+			Size::new(8, 8),
+		);
+		let mut harness = match harness {
+			Ok(harness) => harness,
+			Err(Error::NoAdapter) => return,
+			Err(error) => panic!("{error:?}"),
+		};
+
+		let press =
+			Event::KeyPressed { key: KeyCode::F12, physical: KeyCode::F12, is_repeat: false };
+		harness.apply_input(&press);
+		harness.tick(Duration::from_millis(16));
+		harness.apply_input(&Event::KeyReleased { key: KeyCode::F12, physical: KeyCode::F12 });
+		harness.apply_input(&press);
+		harness.tick(Duration::from_millis(16));
+		drop(harness);
+
+		let mut names: Vec<_> = fs::read_dir(&directory)
+			.unwrap()
+			.map(|entry| entry.unwrap().file_name().into_string().unwrap())
+			.collect();
+		fs::remove_dir_all(&directory).unwrap();
+		names.sort();
+		assert_eq!(names.len(), 2, "{names:?}");
+		for name in names {
+			assert!(name.starts_with("screenshot-"), "{name}");
+			assert_eq!(Path::new(&name).extension(), Some("png".as_ref()));
+		}
+	}
+}
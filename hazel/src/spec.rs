@@ -0,0 +1,168 @@
+use std::time::Duration;
+
+use wgpu::{Backends, CompareFunction, Features, Limits, PowerPreference, TextureFormat};
+use winit::{
+	dpi::{LogicalSize, PhysicalPosition, PhysicalSize},
+	monitor::MonitorHandle,
+	window::WindowAttributes,
+};
+
+/// When the window is redrawn.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum RedrawPolicy {
+	/// Redraw as often as the present mode allows, for games and anything animated.
+	#[default]
+	Continuous,
+	/// Redraw only after an event, a resize, or a call to
+	/// [`crate::context::LayerContext::request_redraw`], and sleep otherwise. Saves power for
+	/// mostly static tools and editors.
+	OnDemand,
+}
+
+/// The size the window opens with.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WindowSize {
+	/// A size in logical pixels, scaled by the monitor's scale factor.
+	Fixed(u32, u32),
+	/// A fraction of the monitor's resolution in both dimensions, e.g. `0.7`, clamped to
+	/// `0.1..=1.0`. winit does not report the work area, so the taskbar or dock is not
+	/// subtracted.
+	Fraction(f32),
+	/// Maximized on the monitor.
+	Maximized,
+}
+
+impl WindowSize {
+	/// Used by [`WindowSize::Fraction`] when there is no monitor to take a fraction of.
+	pub const FALLBACK: WindowSize = WindowSize::Fixed(1280, 720);
+
+	/// Sizes and centers the window on `monitor`, the primary monitor if the platform has one.
+	pub(crate) fn apply(
+		self,
+		attributes: WindowAttributes,
+		monitor: Option<&MonitorHandle>,
+	) -> WindowAttributes {
+		let size = match (self, monitor) {
+			(WindowSize::Maximized, _) => return attributes.with_maximized(true),
+			(WindowSize::Fraction(_), None) => return WindowSize::FALLBACK.apply(attributes, None),
+			(WindowSize::Fixed(width, height), None) => {
+				return attributes.with_inner_size(LogicalSize::new(width, height));
+			},
+			(WindowSize::Fixed(width, height), Some(monitor)) => {
+				LogicalSize::new(width, height).to_physical::<u32>(monitor.scale_factor())
+			},
+			(WindowSize::Fraction(fraction), Some(monitor)) => {
+				let fraction = f64::from(fraction.clamp(0.1, 1.0));
+				let PhysicalSize { width, height } = monitor.size();
+				PhysicalSize::new(f64::from(width) * fraction, f64::from(height) * fraction).cast()
+			},
+		};
+
+		let Some(monitor) = monitor
+		else {
+			return attributes.with_inner_size(size);
+		};
+		// half of any u32 fits in an i32
+		let (position, monitor_size) = (monitor.position(), monitor.size());
+		let x = position.x + (monitor_size.width.saturating_sub(size.width) / 2).cast_signed();
+		let y = position.y + (monitor_size.height.saturating_sub(size.height) / 2).cast_signed();
+		attributes.with_inner_size(size).with_position(PhysicalPosition::new(x, y))
+	}
+}
+
+/// Configuration of the window and the GPU device behind it, passed to [`crate::run_with`].
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Debug)]
+pub struct WindowSpec {
+	pub title: String,
+	/// The size the window opens with, centered on the primary monitor. Wayland places windows
+	/// itself.
+	pub size: WindowSize,
+	/// Whether to prefer an integrated (`LowPower`) or discrete (`HighPerformance`) GPU.
+	pub power_preference: PowerPreference,
+	/// The graphics APIs to choose an adapter from, e.g. `Backends::VULKAN` for consistent
+	/// behavior or `Backends::GL` for old hardware. Falls back to all backends with a warning if
+	/// none of these has an adapter. The chosen backend is logged with the adapter.
+	pub backends: Backends,
+	/// Request a software adapter from the start. Even when `false`, the software adapter is
+	/// tried if no hardware adapter is found.
+	pub force_fallback_adapter: bool,
+	/// Device features to enable, e.g. `POLYGON_MODE_LINE` for wireframe rendering.
+	pub required_features: Features,
+	pub required_limits: Limits,
+	/// The depth test layers should build their pipelines with, see
+	/// [`crate::context::LayerContext::depth_stencil_state`]. `Some` allocates a `Depth32Float`
+	/// depth buffer that is attached to the shared render pass and cleared to `1.0` every frame.
+	/// 2D-only applications can leave it at `None` to skip the allocation.
+	pub depth_compare: Option<CompareFunction>,
+	/// The number of samples per pixel for multisample anti-aliasing: 1, 2, 4 or 8. Counts the
+	/// adapter does not support fall back to the next lower supported one. Pipelines must use
+	/// [`crate::context::LayerContext::multisample_state`] to match.
+	pub sample_count: u32,
+	/// The scroll offset per line for mouse wheels that scroll in lines rather than pixels.
+	pub scroll_line_scale: f32,
+	/// Forces a surface format, if the surface supports it. By default an sRGB format is chosen,
+	/// which encodes the linear colors layers output on write.
+	pub surface_format: Option<TextureFormat>,
+	pub redraw_policy: RedrawPolicy,
+	/// Caps the frames per second by sleeping for the rest of each frame, e.g. to save battery.
+	/// Only applies to present modes that do not wait for vsync, `Immediate` and `Mailbox`;
+	/// with `Fifo` the display already limits the frame rate, so the cap is ignored rather than
+	/// stacking a second wait on top of vsync.
+	pub frame_rate_limit: Option<u32>,
+	/// Logs panics through the engine logger, and its sinks, before the default panic output.
+	/// Turn off to keep a panic hook of your own untouched.
+	pub log_panics: bool,
+	/// Runs `on_update` in steps of this length, as many as fit in the time since the previous
+	/// frame, instead of once per frame. Keeps game logic deterministic regardless of frame
+	/// rate; layers interpolate between steps in `on_render` with
+	/// [`crate::context::LayerContext::interpolation_alpha`].
+	pub fixed_timestep: Option<Duration>,
+	/// Whether the window has a title bar and border. Wayland compositors without server-side
+	/// decorations draw none either way.
+	pub decorations: bool,
+	/// Makes the window see-through wherever the clear color and the layers leave it less than
+	/// fully opaque. Needs a surface that supports a non-opaque alpha mode, which X11 without a
+	/// compositor and most GL drivers lack; the window then stays opaque with a warning. Not
+	/// supported on the web.
+	pub transparent: bool,
+	/// Keeps the window above all others. Ignored on Wayland, where clients cannot set it.
+	pub always_on_top: bool,
+	/// Keeps only the latest of consecutive `MouseMoved` events, dispatched before the next
+	/// other event or frame, so a burst of cursor samples costs one pass through the layers.
+	/// Clicks, keys and `MouseMotionRaw` are never dropped. Off by default, since drawing apps
+	/// want every sample; with it on, drag deltas computed from consecutive positions cover a
+	/// whole frame rather than a single sample.
+	pub coalesce_mouse_moves: bool,
+	/// Panics on GPU errors not caught with an error scope, after logging them. On by default in
+	/// debug builds, to stop right at a broken pipeline; release builds keep running and only
+	/// log the error, though rendering may be wrong from then on.
+	pub panic_on_gpu_error: bool,
+}
+
+impl Default for WindowSpec {
+	fn default() -> Self {
+		WindowSpec {
+			title: "Hazel".to_string(),
+			size: WindowSize::FALLBACK,
+			power_preference: PowerPreference::default(),
+			backends: Backends::all(),
+			force_fallback_adapter: false,
+			required_features: Features::empty(),
+			required_limits: Limits::default(),
+			depth_compare: None,
+			sample_count: 1,
+			scroll_line_scale: 2.0,
+			surface_format: None,
+			redraw_policy: RedrawPolicy::default(),
+			frame_rate_limit: None,
+			log_panics: true,
+			fixed_timestep: None,
+			decorations: true,
+			transparent: false,
+			always_on_top: false,
+			coalesce_mouse_moves: false,
+			panic_on_gpu_error: cfg!(debug_assertions),
+		}
+	}
+}
@@ -0,0 +1,230 @@
+use std::{
+	collections::VecDeque,
+	ops::Mul,
+	sync::atomic::{AtomicBool, Ordering},
+	time::{Duration, Instant},
+};
+
+/// A span of time, usually the time step between two frames.
+///
+/// Multiplying with an `f32` scales it in seconds, so `velocity * time` yields a distance.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Time(Duration);
+
+impl Time {
+	#[must_use]
+	pub fn seconds(self) -> f32 {
+		self.0.as_secs_f32()
+	}
+
+	#[must_use]
+	pub fn millis(self) -> f32 {
+		self.0.as_secs_f32() * 1000.0
+	}
+
+	#[must_use]
+	pub fn duration(self) -> Duration {
+		self.0
+	}
+}
+
+impl From<Duration> for Time {
+	fn from(value: Duration) -> Self {
+		Time(value)
+	}
+}
+
+impl From<Time> for Duration {
+	fn from(value: Time) -> Self {
+		value.0
+	}
+}
+
+impl Mul<f32> for Time {
+	type Output = f32;
+
+	fn mul(self, rhs: f32) -> Self::Output {
+		self.seconds() * rhs
+	}
+}
+
+impl Mul<Time> for f32 {
+	type Output = f32;
+
+	fn mul(self, rhs: Time) -> Self::Output {
+		self * rhs.seconds()
+	}
+}
+
+static REDUCED_MOTION: AtomicBool = AtomicBool::new(false);
+
+/// Asks layers to skip time-based animation, for accessibility or deterministic tests. Off by
+/// default.
+///
+/// This is a convention rather than something the engine enforces: layers check
+/// [`is_reduced_motion`] and jump straight to the end state of an animation, e.g. snap a camera
+/// to its target or show a static indicator instead of a spinner. Movement driven by the user,
+/// like a player walking, is not animation and keeps going.
+pub fn set_reduced_motion(reduced: bool) {
+	REDUCED_MOTION.store(reduced, Ordering::Relaxed);
+}
+
+/// Whether animation should be skipped, see [`set_reduced_motion`].
+#[must_use]
+pub fn is_reduced_motion() -> bool {
+	REDUCED_MOTION.load(Ordering::Relaxed)
+}
+
+/// Upper bound of [`FrameStats::delta_time`], so a long stall, e.g. at a breakpoint, does not
+/// turn into one huge simulation step.
+pub const MAX_DELTA_TIME: Duration = Duration::from_millis(100);
+
+/// Number of frame times kept for [`FrameStats::frame_times`].
+pub const FRAME_HISTORY: usize = 240;
+
+/// Frame timings of the main loop.
+///
+/// Frames are timed from the start of one frame to the start of the next, so a frame time
+/// includes update, render, present and any vsync wait.
+pub struct FrameStats {
+	last_frame_start: Option<Instant>,
+	frame_times: VecDeque<Duration>,
+	gpu_frame_time: Option<Duration>,
+}
+
+impl FrameStats {
+	pub(crate) fn new() -> Self {
+		FrameStats {
+			last_frame_start: None,
+			frame_times: VecDeque::with_capacity(FRAME_HISTORY),
+			gpu_frame_time: None,
+		}
+	}
+
+	pub(crate) fn begin_frame(&mut self) {
+		let now = Instant::now();
+		if let Some(last_frame_start) = self.last_frame_start.replace(now) {
+			self.push_frame_time(now - last_frame_start);
+		}
+	}
+
+	/// Records a frame time without reading the clock, for frames stepped by hand.
+	pub(crate) fn push_frame_time(&mut self, frame_time: Duration) {
+		if self.frame_times.len() == FRAME_HISTORY {
+			self.frame_times.pop_front();
+		}
+		self.frame_times.push_back(frame_time);
+	}
+
+	/// The time since the start of the current frame.
+	pub(crate) fn frame_elapsed(&self) -> Duration {
+		self.last_frame_start.map(|start| start.elapsed()).unwrap_or_default()
+	}
+
+	/// The duration of the previous frame, or zero on the first frame.
+	#[must_use]
+	pub fn frame_time(&self) -> Duration {
+		self.frame_times.back().copied().unwrap_or_default()
+	}
+
+	/// The duration of the previous frame, clamped to [`MAX_DELTA_TIME`].
+	#[must_use]
+	pub fn delta_time(&self) -> Time {
+		self.frame_time().min(MAX_DELTA_TIME).into()
+	}
+
+	/// Frames per second, averaged over the frames of the last second.
+	#[must_use]
+	pub fn fps(&self) -> f32 {
+		let mut elapsed = Duration::ZERO;
+		let mut frames = 0u16;
+		for frame_time in self.frame_times.iter().rev() {
+			if elapsed >= Duration::from_secs(1) {
+				break;
+			}
+			elapsed += *frame_time;
+			frames += 1;
+		}
+
+		if elapsed.is_zero() {
+			0.0
+		}
+		else {
+			f32::from(frames) / elapsed.as_secs_f32()
+		}
+	}
+
+	/// The time the GPU spent in the shared render pass of a recent frame, measured with
+	/// timestamp queries. Lags a frame or two behind [`FrameStats::frame_time`], since the
+	/// timestamps are read back without waiting for the GPU. `None` unless the device has
+	/// `TIMESTAMP_QUERY`, which the `profile` feature enables where the adapter supports it.
+	#[must_use]
+	pub fn gpu_frame_time(&self) -> Option<Duration> {
+		self.gpu_frame_time
+	}
+
+	pub(crate) fn set_gpu_frame_time(&mut self, gpu_frame_time: Duration) {
+		self.gpu_frame_time = Some(gpu_frame_time);
+	}
+
+	/// The last [`FRAME_HISTORY`] frame times, oldest first.
+	#[must_use]
+	pub fn frame_times(&self) -> impl ExactSizeIterator<Item = Duration> + '_ {
+		self.frame_times.iter().copied()
+	}
+}
+
+/// Splits frame time into the fixed update steps of [`crate::WindowSpec::fixed_timestep`],
+/// carrying the remainder over to the next frame.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct FixedTimestep {
+	step: Duration,
+	/// Frame time not yet consumed by steps.
+	lag: Duration,
+}
+
+impl FixedTimestep {
+	/// `step` must not be zero.
+	pub(crate) fn new(step: Duration) -> Self {
+		FixedTimestep { step, lag: Duration::ZERO }
+	}
+
+	pub(crate) fn step(self) -> Duration {
+		self.step
+	}
+
+	/// Adds the time of a frame and returns the number of steps due.
+	pub(crate) fn advance(&mut self, frame_time: Duration) -> u32 {
+		self.lag += frame_time;
+		let mut steps = 0;
+		while self.lag >= self.step {
+			self.lag -= self.step;
+			steps += 1;
+		}
+		steps
+	}
+
+	/// How far the remainder lies into the next step, from 0 to 1.
+	pub(crate) fn alpha(self) -> f32 {
+		self.lag.as_secs_f32() / self.step.as_secs_f32()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::FixedTimestep;
+
+	#[test]
+	fn fixed_steps_carry_the_remainder() {
+		let mut fixed_timestep = FixedTimestep::new(Duration::from_millis(10));
+
+		assert_eq!(fixed_timestep.advance(Duration::from_millis(4)), 0);
+		assert!((fixed_timestep.alpha() - 0.4).abs() < 1e-6);
+		assert_eq!(fixed_timestep.advance(Duration::from_millis(31)), 3);
+		assert!((fixed_timestep.alpha() - 0.5).abs() < 1e-6);
+		assert_eq!(fixed_timestep.advance(Duration::from_millis(5)), 1);
+		assert!(fixed_timestep.alpha().abs() < 1e-6);
+	}
+}
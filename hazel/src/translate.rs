@@ -0,0 +1,175 @@
+//! Translation of winit events into engine events, kept free of window and event loop state.
+
+use winit::{
+	dpi::{PhysicalPosition, PhysicalSize},
+	event::{ElementState, MouseScrollDelta, WindowEvent},
+};
+
+use crate::{event::Event, keyboard, KeyCode};
+
+/// Translates a window event, or returns `None` for events without an engine counterpart.
+///
+/// `scale_factor` is the current scale factor of the window, reported with resizes.
+pub(crate) fn window_event(
+	winit_event: WindowEvent,
+	scroll_line_scale: f32,
+	scale_factor: f64,
+) -> Option<Event> {
+	let event = match winit_event {
+		WindowEvent::CloseRequested => Event::WindowClose,
+
+		WindowEvent::KeyboardInput { event, .. } => {
+			let key = KeyCode::logical(&event.logical_key, event.location);
+			if key == KeyCode::Unknown {
+				keyboard::trace_unmapped(&event.logical_key);
+			}
+			let physical = KeyCode::from(event.physical_key);
+			if physical == KeyCode::Unknown {
+				keyboard::trace_unmapped(&event.physical_key);
+			}
+			match event.state {
+				ElementState::Pressed => {
+					Event::KeyPressed { key, physical, is_repeat: event.repeat }
+				},
+				ElementState::Released => Event::KeyReleased { key, physical },
+			}
+		},
+
+		WindowEvent::MouseInput { state, button, .. } => match state {
+			ElementState::Pressed => Event::MouseButtonPressed(button),
+			ElementState::Released => Event::MouseButtonReleased(button),
+		},
+
+		WindowEvent::MouseWheel { delta, .. } => match delta {
+			MouseScrollDelta::LineDelta(x, y) => Event::MouseScrolled {
+				x_offset: x * scroll_line_scale,
+				y_offset: y * scroll_line_scale,
+				is_line_delta: true,
+			},
+			MouseScrollDelta::PixelDelta(PhysicalPosition { x, y }) => Event::MouseScrolled {
+				x_offset: x as f32,
+				y_offset: y as f32,
+				is_line_delta: false,
+			},
+		},
+
+		WindowEvent::CursorMoved { position: PhysicalPosition { x, y }, .. } => {
+			Event::MouseMoved { x: x as f32, y: y as f32 }
+		},
+
+		WindowEvent::ModifiersChanged(modifiers) => {
+			Event::ModifiersChanged(modifiers.state().into())
+		},
+
+		WindowEvent::Resized(PhysicalSize { width, height }) => {
+			Event::WindowResize { width, height, scale_factor }
+		},
+
+		WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+			Event::WindowScaleChanged { scale_factor }
+		},
+
+		WindowEvent::DroppedFile(path) => Event::FileDropped(path),
+		WindowEvent::HoveredFile(path) => Event::FileHovered(path),
+		WindowEvent::HoveredFileCancelled => Event::FileHoverCancelled,
+
+		_ => return None,
+	};
+	Some(event)
+}
+
+/// A `KeyTyped` event for every printable character of `text`.
+pub(crate) fn typed_text(text: &str) -> impl Iterator<Item = Event> + '_ {
+	text.chars().filter(|it| !it.is_control()).map(Event::KeyTyped)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::path::PathBuf;
+
+	use winit::{
+		dpi::{PhysicalPosition, PhysicalSize},
+		event::{DeviceId, ElementState, MouseButton, MouseScrollDelta, TouchPhase, WindowEvent},
+	};
+
+	use super::{typed_text, window_event};
+	use crate::event::Event;
+
+	fn translate(winit_event: WindowEvent) -> Option<Event> {
+		window_event(winit_event, 20.0, 1.5)
+	}
+
+	#[test]
+	fn window_events() {
+		assert_eq!(translate(WindowEvent::CloseRequested), Some(Event::WindowClose));
+		assert_eq!(
+			translate(WindowEvent::Resized(PhysicalSize::new(1280, 720))),
+			Some(Event::WindowResize { width: 1280, height: 720, scale_factor: 1.5 })
+		);
+
+		let path = PathBuf::from("assets/level.json");
+		assert_eq!(
+			translate(WindowEvent::DroppedFile(path.clone())),
+			Some(Event::FileDropped(path.clone()))
+		);
+		assert_eq!(
+			translate(WindowEvent::HoveredFile(path.clone())),
+			Some(Event::FileHovered(path))
+		);
+		assert_eq!(translate(WindowEvent::HoveredFileCancelled), Some(Event::FileHoverCancelled));
+	}
+
+	#[test]
+	fn mouse_events() {
+		let position = PhysicalPosition::new(120.5, 80.0);
+		assert_eq!(
+			translate(WindowEvent::CursorMoved { device_id: DeviceId::dummy(), position }),
+			Some(Event::MouseMoved { x: 120.5, y: 80.0 })
+		);
+
+		let input = |state| WindowEvent::MouseInput {
+			device_id: DeviceId::dummy(),
+			state,
+			button: MouseButton::Right,
+		};
+		assert_eq!(
+			translate(input(ElementState::Pressed)),
+			Some(Event::MouseButtonPressed(MouseButton::Right))
+		);
+		assert_eq!(
+			translate(input(ElementState::Released)),
+			Some(Event::MouseButtonReleased(MouseButton::Right))
+		);
+	}
+
+	#[test]
+	fn scrolling_scales_lines_but_not_pixels() {
+		let wheel = |delta| WindowEvent::MouseWheel {
+			device_id: DeviceId::dummy(),
+			delta,
+			phase: TouchPhase::Moved,
+		};
+		assert_eq!(
+			translate(wheel(MouseScrollDelta::LineDelta(0.5, -1.0))),
+			Some(Event::MouseScrolled { x_offset: 10.0, y_offset: -20.0, is_line_delta: true })
+		);
+		assert_eq!(
+			translate(wheel(MouseScrollDelta::PixelDelta(PhysicalPosition::new(3.0, -7.5)))),
+			Some(Event::MouseScrolled { x_offset: 3.0, y_offset: -7.5, is_line_delta: false })
+		);
+	}
+
+	#[test]
+	fn other_events_have_no_counterpart() {
+		assert_eq!(translate(WindowEvent::Focused(true)), None);
+		assert_eq!(translate(WindowEvent::RedrawRequested), None);
+		assert_eq!(translate(WindowEvent::Destroyed), None);
+	}
+
+	#[test]
+	fn typed_text_skips_control_characters() {
+		let events: Vec<_> = typed_text("a\u{8}é\r\n\t!").collect();
+		assert_eq!(events, [Event::KeyTyped('a'), Event::KeyTyped('é'), Event::KeyTyped('!')]);
+		assert_eq!(typed_text("").count(), 0);
+	}
+}
@@ -0,0 +1,223 @@
+//! Interpolation and tweening for game and UI animation.
+
+use std::time::Duration;
+
+use crate::{math::Vec2, time::Time, Color};
+
+/// Linear interpolation between two values, `t = 0.0` yielding `self` and `t = 1.0` yielding
+/// `to`. Values of `t` outside `0.0..=1.0` extrapolate.
+pub trait Lerp: Copy {
+	#[must_use]
+	fn lerp(self, to: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+	fn lerp(self, to: Self, t: f32) -> Self {
+		self + (to - self) * t
+	}
+}
+
+impl Lerp for Vec2 {
+	fn lerp(self, to: Self, t: f32) -> Self {
+		Vec2::lerp(self, to, t)
+	}
+}
+
+/// Interpolates the linear components, which blends evenly, unlike interpolating sRGB values.
+impl Lerp for Color {
+	fn lerp(self, to: Self, t: f32) -> Self {
+		Color::linear(
+			self.r.lerp(to.r, t),
+			self.g.lerp(to.g, t),
+			self.b.lerp(to.b, t),
+			self.a.lerp(to.a, t),
+		)
+	}
+}
+
+/// How a [`Tween`] progresses over its duration.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Easing {
+	#[default]
+	Linear,
+	/// Starts slow and speeds up, quadratically.
+	EaseIn,
+	/// Starts fast and slows down, quadratically.
+	EaseOut,
+	/// Slow at both ends, quadratically.
+	EaseInOut,
+	CubicIn,
+	CubicOut,
+	CubicInOut,
+}
+
+impl Easing {
+	/// Maps linear progress in `0.0..=1.0` to eased progress, with `0.0` and `1.0` fixed.
+	#[must_use]
+	pub fn apply(self, t: f32) -> f32 {
+		let t = t.clamp(0.0, 1.0);
+		match self {
+			Easing::Linear => t,
+			Easing::EaseIn => t * t,
+			Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+			Easing::EaseInOut => {
+				if t < 0.5 {
+					2.0 * t * t
+				}
+				else {
+					1.0 - 2.0 * (1.0 - t) * (1.0 - t)
+				}
+			},
+			Easing::CubicIn => t * t * t,
+			Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+			Easing::CubicInOut => {
+				if t < 0.5 {
+					4.0 * t * t * t
+				}
+				else {
+					1.0 - 4.0 * (1.0 - t).powi(3)
+				}
+			},
+		}
+	}
+}
+
+/// An animation from one value to another over a duration, e.g. a panel sliding in.
+///
+/// Advance it from `on_update` with [`crate::context::LayerContext::delta_time`] and read
+/// [`Tween::value`] when rendering.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Tween<T: Lerp> {
+	from: T,
+	to: T,
+	duration: Duration,
+	easing: Easing,
+	elapsed: Duration,
+}
+
+impl<T: Lerp> Tween<T> {
+	/// A linear tween, see [`Tween::with_easing`].
+	#[must_use]
+	pub fn new(from: T, to: T, duration: Duration) -> Self {
+		Tween { from, to, duration, easing: Easing::Linear, elapsed: Duration::ZERO }
+	}
+
+	#[must_use]
+	pub fn with_easing(self, easing: Easing) -> Self {
+		Tween { easing, ..self }
+	}
+
+	/// Moves the tween forward, stopping at the end.
+	pub fn advance(&mut self, delta_time: Time) {
+		self.elapsed = (self.elapsed + delta_time.duration()).min(self.duration);
+	}
+
+	/// Starts the tween over from the beginning.
+	pub fn restart(&mut self) {
+		self.elapsed = Duration::ZERO;
+	}
+
+	/// The value at `elapsed` into the tween, independent of how far it was advanced.
+	#[must_use]
+	pub fn sample(&self, elapsed: Duration) -> T {
+		// `apply` clamps, so sampling past the end holds the end value
+		self.from.lerp(self.to, self.easing.apply(self.progress_at(elapsed)))
+	}
+
+	/// The current value. With [`crate::set_reduced_motion`] on, this is always the end value,
+	/// so the animation is skipped.
+	#[must_use]
+	pub fn value(&self) -> T {
+		if crate::is_reduced_motion() {
+			self.to
+		}
+		else {
+			self.sample(self.elapsed)
+		}
+	}
+
+	/// Progress through the duration, from `0.0` to `1.0`, before easing.
+	#[must_use]
+	pub fn progress(&self) -> f32 {
+		self.progress_at(self.elapsed)
+	}
+
+	#[must_use]
+	pub fn is_finished(&self) -> bool {
+		self.elapsed >= self.duration
+	}
+
+	fn progress_at(&self, elapsed: Duration) -> f32 {
+		if self.duration.is_zero() {
+			1.0
+		}
+		else {
+			elapsed.as_secs_f32() / self.duration.as_secs_f32()
+		}
+	}
+}
+
+#[cfg(test)]
+// the endpoints are exact, not approximations
+#[allow(clippy::float_cmp)]
+mod tests {
+	use std::time::Duration;
+
+	use super::{Easing, Tween};
+	use crate::time::Time;
+
+	const EASINGS: [Easing; 7] = [
+		Easing::Linear,
+		Easing::EaseIn,
+		Easing::EaseOut,
+		Easing::EaseInOut,
+		Easing::CubicIn,
+		Easing::CubicOut,
+		Easing::CubicInOut,
+	];
+
+	#[test]
+	fn easings_keep_the_endpoints() {
+		for easing in EASINGS {
+			assert_eq!(easing.apply(0.0), 0.0, "{easing:?}");
+			assert_eq!(easing.apply(1.0), 1.0, "{easing:?}");
+			// progress outside the range is clamped
+			assert_eq!(easing.apply(-0.5), 0.0, "{easing:?}");
+			assert_eq!(easing.apply(1.5), 1.0, "{easing:?}");
+		}
+	}
+
+	#[test]
+	fn in_out_easings_are_continuous_at_the_middle() {
+		for easing in [Easing::EaseInOut, Easing::CubicInOut] {
+			let below = easing.apply(0.5 - 1e-4);
+			let above = easing.apply(0.5);
+			assert!((above - 0.5).abs() < 1e-6, "{easing:?}: {above}");
+			assert!((above - below).abs() < 1e-3, "{easing:?}: {below} to {above}");
+		}
+	}
+
+	#[test]
+	fn advance_stops_at_the_duration() {
+		let mut tween = Tween::new(0.0, 10.0, Duration::from_millis(100));
+		tween.advance(Time::from(Duration::from_millis(60)));
+		assert!((tween.progress() - 0.6).abs() < 1e-6);
+		assert!(!tween.is_finished());
+
+		tween.advance(Time::from(Duration::from_millis(60)));
+		assert_eq!(tween.progress(), 1.0);
+		assert!(tween.is_finished());
+		assert_eq!(tween.sample(Duration::from_millis(250)), 10.0);
+
+		tween.restart();
+		assert_eq!(tween.progress(), 0.0);
+	}
+
+	#[test]
+	fn zero_duration_tweens_are_at_the_end() {
+		let tween = Tween::new(2.0, 5.0, Duration::ZERO).with_easing(Easing::CubicIn);
+		assert!(tween.is_finished());
+		assert_eq!(tween.progress(), 1.0);
+		assert_eq!(tween.sample(Duration::ZERO), 5.0);
+	}
+}
@@ -1,25 +1,26 @@
-use hazel::{trace, ActiveEventLoop};
-
-struct Sandbox {}
-
-impl Sandbox {}
-
-impl hazel::Application for Sandbox {}
+use hazel::{layer::EventResult, trace, ActiveEventLoop};
 
 struct ExampleLayer {}
 
 impl hazel::layer::Layer for ExampleLayer {
-	fn name(&self) -> &str { "Example" }
-	fn on_event(&mut self, _event_loop: &ActiveEventLoop, event: &hazel::event::Event) -> bool {
-		trace!("{event:?}");
-		
-		false
+	fn name(&self) -> &'static str {
+		"Example"
+	}
+
+	fn on_event(
+		&mut self,
+		_event_loop: &ActiveEventLoop,
+		event: &hazel::event::Event,
+	) -> EventResult {
+		trace!("{event}");
+
+		EventResult::Observe
 	}
 }
 
 /// # Errors
-pub fn main() -> Result<(), hazel::Error> {
-	hazel::run(Sandbox {}, |layer_stack| {
+pub fn main() -> Result<hazel::ExitReason, hazel::Error> {
+	hazel::run_layers(|layer_stack| {
 		layer_stack.push_layer(ExampleLayer {});
 	})
 }